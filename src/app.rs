@@ -1,46 +1,785 @@
-use crate::{task::Task, types::Priority};
-use crate::storage::Storage;
-use chrono::{Local, NaiveDate};
+use crate::{task::{self, Task}, types::{self, AccentColor, GroupBy, LimitAction, Outcome, Priority, ReportPeriod, SortKey, TaskRef}};
+use crate::export::{self, ExportField, ExportFormat};
+use crate::render::{self, format_priority, Symbols, TaskFormat};
+use crate::import::{self, ImportFormat};
+use crate::notify;
+use crate::prompt;
+use crate::query;
+use crate::template::{self, Template};
+use crate::storage::{self, JournalEvent, StorageBackend};
+use crate::sync;
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, Utc};
+use regex::RegexBuilder;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufWriter, Write};
+use std::fs::File;
+use std::path::Path;
+use unicode_width::UnicodeWidthStr;
 
+/// Prints an informational message unless the app is running in quiet mode.
+/// Warnings and errors should go straight to `eprintln!` instead.
+macro_rules! info {
+    ($app:expr, $($arg:tt)*) => {
+        if !$app.options.quiet {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Global settings shared by every command, resolved from the CLI flags
+#[derive(Debug, Default)]
+pub struct AppOptions {
+    pub quiet: bool,
+    /// Store timestamps in UTC instead of the local timezone
+    pub utc: bool,
+    /// Decorate output with ANSI colors and emoji
+    pub color: bool,
+    /// The current list's color from `list_colors` in the config
+    pub accent: Option<AccentColor>,
+    /// Use ASCII markers instead of emoji
+    pub plain_emoji: bool,
+    /// Reject due dates before today when adding or editing
+    pub strict_dates: bool,
+    /// Merge case variants of tags when loading, as `normalize-tags` does
+    pub normalize_tags: bool,
+    /// strftime format for showing dates, instead of `YYYY-MM-DD`
+    pub date_format: Option<String>,
+    /// Priority given to new tasks added without one
+    pub default_priority: Option<Priority>,
+    /// Most tasks the list may hold, if limited
+    pub max_tasks: Option<usize>,
+    /// What adding past `max_tasks` does
+    pub max_tasks_action: LimitAction,
+    /// Show completed tasks dimmed and struck through when colors are on
+    pub strike_completed: bool,
+    /// The command changes tasks, so fixes made while loading, such as uids
+    /// for old tasks, may be saved straight away
+    pub save_on_load: bool,
+    /// Hold the data file lock from loading the tasks until the command
+    /// finishes, for commands that change them
+    pub lock: bool,
+}
+
+/// Filters and rendering choices for `list`
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    pub urgent: bool,
+    /// How many days ahead counts as urgent
+    pub within: i64,
+    /// Hide completed tasks
+    pub active: bool,
+    /// Print only the number of matching tasks
+    pub count: bool,
+    /// Only show tasks estimated to take at most this many minutes
+    pub max_time: Option<u32>,
+    pub sort: SortKey,
+    /// Flip the final order after sorting
+    pub reverse: bool,
+    pub group_by: Option<GroupBy>,
+    /// Custom line template, see `render_template`
+    pub format: Option<String>,
+    /// Print aligned columns instead of a block per task
+    pub table: bool,
+    /// Print one line per task instead of a block
+    pub compact: bool,
+    /// Show full due dates with how far off they are
+    pub absolute_dates: bool,
+    /// Print one JSON object per task per line
+    pub json_lines: bool,
+    /// Print one tab-separated line per task
+    pub tsv: bool,
+    /// Print only task IDs, one per line
+    pub ids_only: bool,
+    /// End each printed ID or description with NUL instead of a newline
+    pub print0: bool,
+    /// Let `urgent` match completed tasks too
+    pub include_completed: bool,
+    /// Shorten descriptions so each line fits in this many columns
+    pub width: Option<usize>,
+    /// Only completed tasks finished on or after this date
+    pub since: Option<String>,
+    /// Only completed tasks finished on or before this date
+    pub until: Option<String>,
+    /// Only tasks with this color label
+    pub label: Option<AccentColor>,
+}
+
+/// How `search` matches and which tasks it looks through
+#[derive(Debug, Default)]
+pub struct SearchOptions {
+    /// Also search tasks that have been archived
+    pub include_archived: bool,
+    /// Treat the keyword as a regular expression
+    pub regex: bool,
+    /// Match upper and lower case exactly instead of ignoring case
+    pub case_sensitive: bool,
+    /// Leave out completed tasks
+    pub active: bool,
+    /// Order results as `list --sort` would; `None` keeps storage order
+    pub sort: Option<SortKey>,
+    /// Print only task IDs, one per line
+    pub ids_only: bool,
+    /// End each printed ID or description with NUL instead of a newline
+    pub print0: bool,
+}
+
+/// Whether `task` passes the inclusive `--since`/`--until` completion range.
+/// With neither bound set every task passes; with either, only tasks completed
+/// in the range do.
+fn completed_within(task: &Task, since: Option<NaiveDate>, until: Option<NaiveDate>) -> bool {
+    if since.is_none() && until.is_none() {
+        return true;
+    }
+    let Some(completed) = task.completed_at.map(|at| at.date_naive()) else {
+        return false;
+    };
+    since.is_none_or(|since| completed >= since) && until.is_none_or(|until| completed <= until)
+}
+
+/// How many of `completion_days` fall on each day from `start` to `end`
+fn completions_per_day(completion_days: &[NaiveDate], start: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, usize)> {
+    start.iter_days()
+        .take_while(|day| *day <= end)
+        .map(|day| (day, completion_days.iter().filter(|done| **done == day).count()))
+        .collect()
+}
+
+/// Days in a row with a completion, ending today or, if nothing is done yet
+/// today, yesterday: a day doesn't break the streak until it's over
+fn completion_streak(completion_days: &[NaiveDate], today: NaiveDate) -> usize {
+    let mut day = today;
+    if !completion_days.contains(&day) {
+        day = day.pred_opt().unwrap_or(day);
+    }
+    let mut length = 0;
+    while completion_days.contains(&day) {
+        length += 1;
+        day = match day.pred_opt() {
+            Some(previous) => previous,
+            None => break,
+        };
+    }
+    length
+}
+
+/// Widest a description may be in `list --table` when no width is known
+const TABLE_DESCRIPTION_WIDTH: usize = 40;
+
+/// Imports larger than this report their progress as they go
+const IMPORT_PROGRESS_THRESHOLD: usize = 100;
+
+/// Days after today covered by the "Upcoming" section of `all`
+const OVERVIEW_UPCOMING_DAYS: i64 = 7;
+
+/// How many tasks `import` handles between progress lines
+const IMPORT_PROGRESS_STEP: usize = 250;
+
+/// Renders tasks as the aligned columns of `list --table`
+///
+/// # Arguments
+/// * `format` - Its `width` is the total line width to fit, with the description
+///   column absorbing any shortfall; its `date_format` lays out the due dates
+fn task_table(tasks: &[&Task], format: &TaskFormat) -> String {
+    let symbols = format.symbols;
+    const HEADERS: [&str; 6] = ["ID", "Done", "Description", "Due", "Priority", "Tags"];
+    const DESCRIPTION: usize = 2;
+
+    let mut rows: Vec<Vec<String>> = tasks.iter()
+        .map(|task| vec![
+            task.id.to_string(),
+            if task.completed { symbols.done } else { " " }.to_string(),
+            task.description.clone(),
+            task.due_date.map(|due| render::format_date(due, format.date_format, "%Y-%m-%d")).unwrap_or_else(|| "-".to_string()),
+            // Plain labels: ANSI codes would throw off the column widths
+            format_priority(&task.priority, false, symbols),
+            task.tags.join(","),
+        ])
+        .collect();
+
+    let description_width = match format.width {
+        Some(width) => {
+            // Everything except the description, plus the gaps between columns
+            let others: usize = (0..HEADERS.len())
+                .filter(|&column| column != DESCRIPTION)
+                .map(|column| rows.iter()
+                    .map(|row| row[column].width())
+                    .chain([HEADERS[column].width()])
+                    .max()
+                    .unwrap_or(0))
+                .sum::<usize>() + 2 * (HEADERS.len() - 1);
+            width.saturating_sub(others).max(render::MIN_DESCRIPTION_WIDTH)
+        }
+        None => TABLE_DESCRIPTION_WIDTH,
+    };
+    for row in rows.iter_mut() {
+        row[DESCRIPTION] = render::truncate(&row[DESCRIPTION], description_width);
+    }
+    render::table(&HEADERS, &rows)
+}
+
+/// Normalizes every task's tags with `Task::normalize_tags`
+///
+/// # Returns
+/// * `(usize, BTreeMap<String, BTreeSet<String>>)` - How many tasks changed, and
+///   for each resulting tag the differently-cased spellings merged into it
+fn merge_tag_variants(tasks: &mut [Task]) -> (usize, BTreeMap<String, BTreeSet<String>>) {
+    let mut variants: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut changed = 0;
+    for task in tasks.iter_mut() {
+        for tag in task.tags.iter().filter(|tag| tag.to_lowercase() != **tag) {
+            variants.entry(tag.to_lowercase()).or_default().insert(tag.clone());
+        }
+        if task.normalize_tags() {
+            changed += 1;
+        }
+    }
+    (changed, variants)
+}
+
+/// A uid from `task::generate_uid` that no task in `tasks` has
+fn unique_uid(tasks: &[Task]) -> String {
+    loop {
+        let uid = task::generate_uid();
+        if !tasks.iter().any(|task| task.uid.as_deref() == Some(uid.as_str())) {
+            return uid;
+        }
+    }
+}
+
+/// Gives each task without a uid a unique one
+///
+/// # Returns
+/// * `bool` - Whether any task was given a uid
+fn assign_missing_uids(tasks: &mut [Task]) -> bool {
+    let mut assigned = false;
+    for index in 0..tasks.len() {
+        if tasks[index].uid.is_none() {
+            tasks[index].uid = Some(unique_uid(tasks));
+            assigned = true;
+        }
+    }
+    assigned
+}
+
+/// Whether two tasks look like the same piece of work to `import --skip-duplicates`
+fn is_duplicate(a: &Task, b: &Task) -> bool {
+    a.due_date == b.due_date && a.description.to_lowercase() == b.description.to_lowercase()
+}
+
+/// Splits a line of `add-many` input into its description, `#tag` words and
+/// `due:` date
+fn parse_inline_task(line: &str) -> (String, Vec<String>, Option<String>) {
+    let mut words = Vec::new();
+    let mut tags = Vec::new();
+    let mut due = None;
+    for word in line.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            tags.push(tag.to_string());
+        } else if let Some(date) = word.strip_prefix("due:").filter(|date| !date.is_empty()) {
+            due = Some(date.to_string());
+        } else {
+            words.push(word);
+        }
+    }
+    (words.join(" "), tags, due)
+}
+
+/// Reads and parses a file for `import`, reporting any failure
+fn read_import(path: &str, format: ImportFormat) -> Result<Vec<Task>, Outcome> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        eprintln!("Error: could not read '{}': {}", path, e);
+        Outcome::IoError
+    })?;
+    import::parse(&contents, format).map_err(|e| {
+        eprintln!("Error: could not parse '{}': {}", path, e);
+        Outcome::BadInput
+    })
+}
+
+/// Streams tasks to stdout as JSON lines through a buffered writer
+fn print_json_lines<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> Outcome {
+    match export::write_json_lines(tasks, BufWriter::new(io::stdout().lock())) {
+        Ok(()) => Outcome::Success,
+        Err(e) => {
+            eprintln!("Error: could not write tasks: {}", e);
+            Outcome::IoError
+        }
+    }
+}
+
+/// Streams tasks to stdout as tab-separated lines through a buffered writer
+fn print_tsv<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> Outcome {
+    match export::write_tsv(tasks, BufWriter::new(io::stdout().lock())) {
+        Ok(()) => Outcome::Success,
+        Err(e) => {
+            eprintln!("Error: could not write tasks: {}", e);
+            Outcome::IoError
+        }
+    }
+}
+
+/// Prints one field per task for scripts: its ID with `ids_only`, otherwise
+/// its description, each ended by a NUL byte with `print0` or else a newline
+fn print_fields(tasks: &[&Task], ids_only: bool, print0: bool) -> Outcome {
+    let terminator = if print0 { '\0' } else { '\n' };
+    let mut writer = BufWriter::new(io::stdout().lock());
+    let written = tasks.iter()
+        .try_for_each(|task| if ids_only {
+            write!(writer, "{}{}", task.id, terminator)
+        } else {
+            write!(writer, "{}{}", task.description, terminator)
+        })
+        .and_then(|()| writer.flush());
+    match written {
+        Ok(()) => Outcome::Success,
+        Err(e) => {
+            eprintln!("Error: could not write tasks: {}", e);
+            Outcome::IoError
+        }
+    }
+}
+
+/// Orders by due date, soonest first, with undated tasks last
+fn compare_due(a: &Task, b: &Task) -> Ordering {
+    match (a.due_date, b.due_date) {
+        (Some(ad), Some(bd)) => ad.cmp(&bd),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Sorts tasks by `key` (stable, so ties keep storage order), optionally reversed
+fn sort_tasks(tasks: &mut [&Task], key: SortKey, reverse: bool, today: NaiveDate) {
+    tasks.sort_by(|a, b| match key {
+        SortKey::Due => compare_due(a, b),
+        SortKey::Id => a.id.cmp(&b.id),
+        // Option orders None first, so compare b to a to put High first and None last
+        SortKey::Priority => b.priority.cmp(&a.priority).then_with(|| compare_due(a, b)),
+        SortKey::Description => a.description.to_lowercase().cmp(&b.description.to_lowercase()),
+        SortKey::Urgency => b.urgency_score(today).cmp(&a.urgency_score(today)).then_with(|| compare_due(a, b)),
+    });
+    if reverse {
+        tasks.reverse();
+    }
+    // Stable, so the chosen order holds among pinned and among unpinned tasks
+    tasks.sort_by_key(|task| !task.pinned);
+}
+
+/// Buckets already-sorted tasks under group headers, keeping their order within
+/// each group. Without a grouping everything lands in one unnamed group.
+fn group_tasks<'a>(tasks: &[&'a Task], group_by: Option<GroupBy>) -> Vec<(Option<String>, Vec<&'a Task>)> {
+    match group_by {
+        None => vec![(None, tasks.to_vec())],
+        Some(GroupBy::Tag) => {
+            let mut groups: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+            let mut untagged = Vec::new();
+            for &task in tasks {
+                if task.tags.is_empty() {
+                    untagged.push(task);
+                }
+                for tag in &task.tags {
+                    groups.entry(tag.as_str()).or_default().push(task);
+                }
+            }
+            let mut grouped: Vec<(Option<String>, Vec<&Task>)> = groups.into_iter()
+                .map(|(tag, tasks)| (Some(tag.to_string()), tasks))
+                .collect();
+            if !untagged.is_empty() {
+                grouped.push((Some("No tags".to_string()), untagged));
+            }
+            grouped
+        }
+        Some(GroupBy::Priority) => {
+            let levels = [Some(Priority::High), Some(Priority::Medium), Some(Priority::Low), None];
+            levels.into_iter()
+                .map(|level| {
+                    let header = level.as_ref().map_or("No priority", Priority::label).to_string();
+                    let members = tasks.iter().copied().filter(|task| task.priority == level).collect();
+                    (Some(header), members)
+                })
+                .filter(|(_, members): &(Option<String>, Vec<&Task>)| !members.is_empty())
+                .collect()
+        }
+    }
+}
+
+/// Optional fields for a new task, as given to `add`
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    pub priority: Option<Priority>,
+    /// Tags (comma-separated)
+    pub tags: Option<String>,
+    /// Due date (see `types::parse_date`)
+    pub due: Option<String>,
+    pub estimate: Option<u32>,
+    pub label: Option<AccentColor>,
+    /// Add even if an active task has the same description
+    pub force: bool,
+}
+
+/// Field changes requested by `edit`; `None` leaves a field unchanged
+#[derive(Debug, Default)]
+pub struct EditOptions {
+    pub description: Option<String>,
+    /// Take the new description from `$EDITOR`, starting from the current one
+    pub editor: bool,
+    pub priority: Option<Priority>,
+    /// Replace all tags (comma-separated); an empty string clears them
+    pub tags: Option<String>,
+    /// Append tags (comma-separated)
+    pub add_tags: Option<String>,
+    /// Remove individual tags (comma-separated, case-insensitive)
+    pub remove_tags: Option<String>,
+    /// New due date (see `types::parse_date`); an empty string clears it
+    pub due: Option<String>,
+    pub estimate: Option<u32>,
+    pub label: Option<AccentColor>,
+    pub clear_priority: bool,
+    pub clear_tags: bool,
+    pub clear_due: bool,
+    pub clear_label: bool,
+}
+
+/// Which tasks `bulk-edit` changes and how. Filters combine, so a task must
+/// pass all of the ones that are set.
+#[derive(Debug, Default)]
+pub struct BulkEditOptions {
+    pub tag: Option<String>,
+    pub overdue: bool,
+    pub completed: bool,
+    pub set_priority: Option<Priority>,
+    /// Tags to append (comma-separated)
+    pub add_tags: Option<String>,
+    /// Days to push due dates back by
+    pub snooze: Option<i64>,
+}
+
+/// Fills a `list --format` template for one task.
+///
+/// Supported placeholders: `{id}`, `{uid}`, `{desc}`, `{due}`, `{priority}`,
+/// `{tags}` and `{status}`. Unknown placeholders are copied through literally.
+/// `{due}` follows `date_format`, like due dates elsewhere.
+fn render_template(template: &str, task: &Task, symbols: &Symbols, date_format: Option<&str>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start..];
+        let Some(end) = after.find('}') else {
+            rest = after;
+            break;
+        };
+
+        let value = match &after[1..end] {
+            "id" => Some(task.id.to_string()),
+            "uid" => Some(task.uid.clone().unwrap_or_default()),
+            "desc" => Some(task.description.clone()),
+            "due" => Some(task.due_date.map(|due| render::format_date(due, date_format, "%Y-%m-%d")).unwrap_or_default()),
+            "priority" => Some(task.priority.as_ref().map_or("", Priority::label).to_string()),
+            "tags" => Some(task.tags.join(",")),
+            "status" => Some(if task.completed { symbols.done } else { " " }.to_string()),
+            _ => None,
+        };
+
+        match value {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&after[..=end]),
+        }
+        rest = &after[end + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
 
 pub struct TodoApp {
     tasks: Vec<Task>,
     next_id: u32,
-    storage: Storage,
+    storage: Box<dyn StorageBackend>,
+    options: AppOptions,
+    /// The date all due-date comparisons are made against. Captured once at
+    /// startup so a command sees a consistent "today" from start to finish.
+    today: NaiveDate,
+    /// Set between `begin_batch` and `commit_batch`; saves are deferred meanwhile
+    batching: bool,
+    /// A save was requested while batching and hasn't been written yet
+    unsaved: bool,
+    /// The data file lock, held by commands that change tasks from loading
+    /// them until the command finishes
+    lock: Option<File>,
+}
+
+/// Loads the tasks and brings older data up to date in memory
+fn load_tasks(storage: &dyn StorageBackend, options: &AppOptions) -> Vec<Task> {
+    let mut tasks = storage.load_tasks();
+
+    // Migrate timestamps written in local time; the converted values are
+    // persisted by the next save
+    if options.utc {
+        for task in tasks.iter_mut() {
+            task.convert_to_utc();
+        }
+    }
+    // Also persisted by the next save. Tags stored before they were kept
+    // sorted are sorted too, so every command shows them in the same order.
+    if options.normalize_tags {
+        merge_tag_variants(&mut tasks);
+    }
+    for task in tasks.iter_mut() {
+        task.sort_tags();
+    }
+    // Tasks from before uids existed get one now. It's saved right away so
+    // the uid stays the same from then on, but only by commands that write
+    // anyway: listing or exporting never rewrites the data file.
+    if assign_missing_uids(&mut tasks) && options.save_on_load && storage.check_writable().is_ok() {
+        storage.save_tasks(&tasks);
+    }
+    tasks
+}
+
+/// The ID the next added task gets
+fn next_free_id(tasks: &[Task]) -> u32 {
+    tasks.iter()
+        .map(|task| task.id)
+        .max()
+        .unwrap_or(0) + 1
 }
 
 impl TodoApp {
-    pub fn new() -> Self {
-        let storage = Storage::new();
-        let tasks = storage.load_tasks();
-        let next_id = tasks.iter()
-            .map(|task| task.id)
-            .max()
-            .unwrap_or(0) + 1;
+    pub fn new(options: AppOptions, storage: Box<dyn StorageBackend>) -> Self {
+        // Taken before loading so no other process can save in between
+        let lock = if options.lock { storage.lock() } else { None };
+        let tasks = load_tasks(storage.as_ref(), &options);
 
         Self {
+            next_id: next_free_id(&tasks),
             tasks,
-            next_id,
             storage,
+            options,
+            today: Local::now().date_naive(),
+            batching: false,
+            unsaved: false,
+            lock,
+        }
+    }
+
+    /// Runs `ask`, which waits on the user, with the data file lock released
+    /// so other `todo` processes aren't held up meanwhile. If one of them
+    /// saved in the meantime, the tasks are loaded again afterwards.
+    pub fn without_lock<T>(&mut self, ask: impl FnOnce() -> T) -> T {
+        let Some(lock) = self.lock.take() else {
+            return ask();
+        };
+        let seen = self.storage.modified_time();
+        drop(lock);
+
+        let answer = ask();
+
+        self.lock = self.storage.lock();
+        if self.storage.modified_time() != seen {
+            self.tasks = load_tasks(self.storage.as_ref(), &self.options);
+            self.next_id = next_free_id(&self.tasks);
+        }
+        answer
+    }
+
+    /// Finds the ID of the task `task` refers to. A uid prefix must match
+    /// exactly one task; otherwise the problem is reported and its outcome returned.
+    pub fn resolve(&self, task: &TaskRef) -> Result<u32, Outcome> {
+        let prefix = match task {
+            TaskRef::Id(id) => return Ok(*id),
+            TaskRef::Uid(prefix) => prefix,
+        };
+        let matches: Vec<&Task> = self.tasks.iter()
+            .filter(|task| task.uid.as_deref().is_some_and(|uid| uid.starts_with(prefix.as_str())))
+            .collect();
+        match matches.as_slice() {
+            [] => {
+                eprintln!("No task has a uid starting with '{}'", prefix);
+                Err(Outcome::NotFound)
+            }
+            [task] => Ok(task.id),
+            _ => {
+                let ids: Vec<String> = matches.iter().map(|task| format!("#{}", task.id)).collect();
+                eprintln!("Error: uid '{}' matches tasks {}; type more of it", prefix, ids.join(", "));
+                Err(Outcome::BadInput)
+            }
+        }
+    }
+
+    /// Defers saving until `commit_batch`, so a run of changes writes the data
+    /// file once instead of once per change
+    pub fn begin_batch(&mut self) {
+        self.batching = true;
+    }
+
+    /// Ends a batch, writing the tasks if anything in it asked to save
+    pub fn commit_batch(&mut self) -> Outcome {
+        self.batching = false;
+        if !std::mem::take(&mut self.unsaved) {
+            return Outcome::Success;
+        }
+        self.save()
+    }
+
+    /// The current time, in UTC or local time depending on the `utc` option
+    fn now(&self) -> DateTime<FixedOffset> {
+        if self.options.utc {
+            Utc::now().fixed_offset()
+        } else {
+            Local::now().fixed_offset()
+        }
+    }
+
+    /// Persists the current task list, reporting a failed write as an I/O error.
+    /// Inside a batch this only records that a save is due.
+    fn save(&mut self) -> Outcome {
+        if self.batching {
+            self.unsaved = true;
+            return Outcome::Success;
+        }
+        if self.storage.save_tasks(&self.tasks) {
+            Outcome::Success
+        } else {
+            Outcome::IoError
+        }
+    }
+
+    /// Persists a single-task change. Backends with a journal append just this
+    /// change; otherwise (or inside a batch) it falls back to a full save.
+    fn record(&mut self, event: JournalEvent) -> Outcome {
+        if self.batching || !self.storage.journaling() {
+            return self.save();
+        }
+        if self.storage.append_event(&event) {
+            Outcome::Success
+        } else {
+            Outcome::IoError
         }
     }
 
-    pub fn add_task(&mut self, description: String, priority_input:Option<Priority>, tag_list:Option<String>, due_date_str: Option<String>) {        
-        let due_date = due_date_str.clone().and_then(|date_str| 
-            NaiveDate::parse_from_str(&date_str, "%Y-%m-%d").ok()
+    /// Adds a task. Unless `force` is set, a description matching an active
+    /// task's (ignoring case) is refused as a likely accidental duplicate.
+    pub fn add_task(&mut self, description: String, options: AddOptions) -> Outcome {
+        if !options.force
+            && let Some(existing) = self.tasks.iter()
+                .find(|task| !task.completed && task.description.to_lowercase() == description.to_lowercase())
+        {
+            eprintln!("Error: task #{} already has this description; pass --force to add it anyway", existing.id);
+            return Outcome::BadInput;
+        }
+
+        if let Some(max) = self.options.max_tasks
+            && self.tasks.len() >= max
+        {
+            match self.options.max_tasks_action {
+                LimitAction::Refuse => {
+                    eprintln!("Error: the list already has {} tasks, the max_tasks limit is {}; archive or remove some first", self.tasks.len(), max);
+                    return Outcome::BadInput;
+                }
+                LimitAction::Warn => {
+                    eprintln!("Warning: the list has {} tasks, over the max_tasks limit of {}", self.tasks.len() + 1, max);
+                }
+            }
+        }
+
+        let due_date = options.due.as_deref().and_then(|date_str|
+            types::parse_date(date_str, self.today)
         );
 
-        if due_date_str.is_some() && due_date.is_none() {
-            println!("Warning: Invalid due date format. Use YYYY-MM-DD.");
-            return;
+        if options.due.is_some() && due_date.is_none() {
+            eprintln!("Warning: Invalid due date format. Use YYYY-MM-DD, 'tomorrow', '+3d' or a weekday.");
+            return Outcome::BadInput;
         }
+        if let Some(due) = due_date
+            && self.options.strict_dates
+            && due < self.today
+        {
+            eprintln!("Error: due date {} is in the past (strict dates are on)", due);
+            return Outcome::BadInput;
+        }
+
+        let tags = options.tags.map(|s| {
+            s.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        }).unwrap_or_default();
+
+        let priority = options.priority.or_else(|| self.options.default_priority.clone());
+        let mut task = Task::new(self.next_id, description, priority, tags, due_date);
+        if self.tasks.iter().any(|existing| existing.uid == task.uid) {
+            task.uid = Some(unique_uid(&self.tasks));
+        }
+        task.estimate_minutes = options.estimate;
+        task.label = options.label;
+        self.tasks.push(task.clone());
+        self.next_id += 1;
+        let outcome = self.record(JournalEvent::Upsert(task));
+
+        info!(self, "Added task #{}: {}", self.next_id - 1, self.tasks.last().unwrap().description);
+        outcome
+    }
 
-        let mut priority = None;
+    /// Adds a task for each non-blank line of a file, saving once at the end
+    ///
+    /// Words such as `#errands` become tags and `due:friday` the due date (any
+    /// form `add --due` accepts); the rest of the line is the description.
+    /// Lines that fail are reported and skipped.
+    pub fn add_many(&mut self, path: &Path, force: bool) -> Outcome {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Error: could not read '{}': {}", path.display(), e);
+                return Outcome::IoError;
+            }
+        };
+
+        let mut outcome = Outcome::Success;
+        let (mut added, mut failed) = (0, 0);
+        self.begin_batch();
+        for (number, line) in (1..).zip(contents.lines()) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (description, tags, due) = parse_inline_task(line);
+            let result = if description.is_empty() {
+                eprintln!("Error: line {} has no description", number);
+                Outcome::BadInput
+            } else {
+                let options = AddOptions {
+                    tags: Some(tags.join(",")),
+                    due,
+                    force,
+                    ..AddOptions::default()
+                };
+                self.add_task(description, options)
+            };
+            if result.is_success() {
+                added += 1;
+            } else {
+                eprintln!("  (skipped line {}: {})", number, line.trim());
+                failed += 1;
+                if outcome.is_success() {
+                    outcome = result;
+                }
+            }
+        }
+        let saved = self.commit_batch();
 
-        if let Some(priority_value) = priority_input {
-            priority = Some(priority_value);
+        if failed > 0 {
+            info!(self, "Added {} tasks from {}, skipped {} lines", added, path.display(), failed);
+        } else {
+            info!(self, "Added {} tasks from {}", added, path.display());
         }
+        if outcome.is_success() { saved } else { outcome }
+    }
 
+    /// Saves a template that `add_from_template` can create tasks from
+    pub fn save_template(&self, name: &str, description: String, priority: Option<Priority>, tag_list: Option<String>, estimate: Option<u32>) -> Outcome {
         let tags = tag_list.map(|s| {
             s.split(',')
                 .map(|s| s.trim().to_string())
@@ -48,35 +787,105 @@ impl TodoApp {
                 .collect()
         }).unwrap_or_default();
 
-        let task = Task::new(self.next_id, description, priority, tags, due_date);
-        self.tasks.push(task);
-        self.next_id += 1;
-        self.storage.save_tasks(&self.tasks);
+        let template = Template { description, priority, tags, estimate_minutes: estimate };
+        match template::save(name, &template) {
+            Ok(path) => {
+                info!(self, "Saved template '{}' to {}", name, path.display());
+                Outcome::Success
+            }
+            Err(e) => {
+                eprintln!("Error: could not save template '{}': {}", name, e);
+                Outcome::IoError
+            }
+        }
+    }
+
+    /// Adds a task with the fields of a saved template
+    pub fn add_from_template(&mut self, name: &str, due: Option<String>, force: bool) -> Outcome {
+        let template = match template::load(name) {
+            Ok(Some(template)) => template,
+            Ok(None) => {
+                eprintln!("Template '{}' not found", name);
+                return Outcome::NotFound;
+            }
+            Err(e) => {
+                eprintln!("Error: could not read template '{}': {}", name, e);
+                return Outcome::IoError;
+            }
+        };
+
+        self.add_task(template.description, AddOptions {
+            priority: template.priority,
+            tags: (!template.tags.is_empty()).then(|| template.tags.join(",")),
+            due,
+            estimate: template.estimate_minutes,
+            label: None,
+            force,
+        })
+    }
 
-        println!("Added task #{}: {}", self.next_id - 1, self.tasks.last().unwrap().description);
+    pub fn list_templates(&self) -> Outcome {
+        match template::names() {
+            Ok(names) if names.is_empty() => {
+                println!("No templates saved yet.");
+                Outcome::Success
+            }
+            Ok(names) => {
+                for name in names {
+                    println!("{}", name);
+                }
+                Outcome::Success
+            }
+            Err(e) => {
+                eprintln!("Error: could not read templates: {}", e);
+                Outcome::IoError
+            }
+        }
     }
 
-    pub fn edit_task(
-        &mut self,
-        id: u32,
-        new_desc: Option<String>,
-        priority_input: Option<Priority>,
-        tags_replace: Option<String>,
-        tags_add: Option<String>,
-        due_date: Option<String>,
-    ) {
+    pub fn edit_task(&mut self, id: u32, changes: EditOptions) -> Outcome {
+        let symbols = self.symbols();
+        let mut edited = None;
+        if changes.editor
+            && let Some(current) = self.tasks.iter().find(|t| t.id == id).map(|t| t.description.clone())
+        {
+            match self.without_lock(|| prompt::edit_in_editor(&current)) {
+                Ok(desc) => edited = Some(desc),
+                Err(e) => {
+                    eprintln!("Error: {}; task not changed", e);
+                    return Outcome::BadInput;
+                }
+            }
+        }
         match self.tasks.iter_mut().find(|t| t.id == id) {
             Some(task) => {
-                if let Some(desc) = new_desc {
+                if let Some(desc) = edited {
+                    task.description = desc;
+                }
+                if let Some(desc) = changes.description {
                     task.description = desc;
                 }
     
-                if let Some(p) = priority_input {
+                if let Some(p) = changes.priority {
                     task.priority = Some(p);
                 }
+                if changes.clear_priority {
+                    task.priority = None;
+                }
+
+                if let Some(minutes) = changes.estimate {
+                    task.estimate_minutes = Some(minutes);
+                }
+
+                if let Some(label) = changes.label {
+                    task.label = Some(label);
+                }
+                if changes.clear_label {
+                    task.label = None;
+                }
     
                 // Replace tags if provided. Empty string clears tags.
-                if let Some(tags_s) = tags_replace {
+                if let Some(tags_s) = changes.tags {
                     let parsed: Vec<String> = tags_s
                         .split(',')
                         .map(|s| s.trim().to_string())
@@ -84,32 +893,49 @@ impl TodoApp {
                         .collect();
                     task.tags = parsed;
                 }
+                if changes.clear_tags {
+                    task.tags.clear();
+                }
     
                 // Append tags if provided
-                if let Some(add_s) = tags_add {
+                if let Some(add_s) = changes.add_tags {
                     let mut parsed_to_add: Vec<String> = add_s
                         .split(',')
                         .map(|s| s.trim().to_string())
                         .filter(|s| !s.is_empty())
                         .collect();
-                    if !parsed_to_add.is_empty() {
-                        task.tags.extend(parsed_to_add.drain(..));
-                        // dedupe while preserving sorted order
-                        task.tags.sort();
-                        task.tags.dedup();
-                    }
+                    task.tags.append(&mut parsed_to_add);
+                }
+
+                // Remove tags last so it composes with replacing and appending
+                if let Some(remove_s) = changes.remove_tags {
+                    let to_remove: Vec<String> = remove_s
+                        .split(',')
+                        .map(|s| s.trim().to_lowercase())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                    task.tags.retain(|tag| !to_remove.contains(&tag.to_lowercase()));
                 }
+                task.sort_tags();
     
+                if changes.clear_due {
+                    task.due_date = None;
+                }
+
                 // Parse due date if provided
-                if let Some(due_s) = due_date {
+                if let Some(due_s) = changes.due {
                     if due_s.is_empty() {
                         task.due_date = None;
                     } else {
-                        match NaiveDate::parse_from_str(&due_s, "%Y-%m-%d") {
-                            Ok(d) => task.due_date = Some(d),
-                            Err(_) => {
-                                println!("Warning: invalid due date '{}'. Use YYYY-MM-DD.", due_s);
-                                return;
+                        match types::parse_date(&due_s, self.today) {
+                            Some(d) if self.options.strict_dates && d < self.today => {
+                                eprintln!("Error: due date {} is in the past (strict dates are on)", d);
+                                return Outcome::BadInput;
+                            }
+                            Some(d) => task.due_date = Some(d),
+                            None => {
+                                eprintln!("Warning: invalid due date '{}'. Use YYYY-MM-DD, 'tomorrow', '+3d' or a weekday.", due_s);
+                                return Outcome::BadInput;
                             }
                         }
                     }
@@ -119,23 +945,17 @@ impl TodoApp {
                 // then drop that borrow before calling save_tasks.
                 let (tags_display, priority_display, desc_clone, due_display) = {
                     let tags_display = if task.tags.is_empty() { "No tags".to_string() } else { task.tags.join(", ") };
-                    let priority_display = match &task.priority {
-                        Some(p) => match p {
-                            Priority::High => "🔴 HIGH".to_string(),
-                            Priority::Medium => "🟡 MED".to_string(),
-                            Priority::Low => "🟢 LOW".to_string(),
-                        },
-                        None => "None".to_string(),
-                    };
+                    let priority_display = format_priority(&task.priority, self.options.color, symbols);
                     let desc_clone = task.description.clone();
-                    let due_display = task.due_date.map_or("No due date".to_string(), |d| d.to_string());
+                    let due_display = task.due_date.map_or("No due date".to_string(), |due| render::format_date(due, self.options.date_format.as_deref(), "%Y-%m-%d"));
                     (tags_display, priority_display, desc_clone, due_display)
                 };
     
                 // Persist changes (mutable borrow ended)
-                self.storage.save_tasks(&self.tasks);
+                let outcome = self.save();
     
-                println!(
+                info!(
+                    self,
                     "Edited task #{}: {}\n  Due: {}\n  Priority: {}\n  Tags: {}",
                     id,
                     desc_clone,
@@ -143,136 +963,1338 @@ impl TodoApp {
                     priority_display,
                     tags_display
                 );
+                outcome
+            }
+            None => {
+                eprintln!("Task #{} not found", id);
+                Outcome::NotFound
             }
-            None => println!("Task #{} not found", id),
         }
     }
 
-    pub fn list_tasks(&self, urgent_only: bool) {
-        let mut tasks_to_show: Vec<&Task> = if urgent_only {
-            self.tasks.iter().filter(|task| task.is_urgent(3)).collect()
-        } else {
-            self.tasks.iter().collect()
+    /// Applies the same changes to every task matching the filters, saving once
+    pub fn bulk_edit(&mut self, options: BulkEditOptions) -> Outcome {
+        let today = self.today;
+        let new_tags: Vec<String> = options.add_tags.as_deref()
+            .map(|tags| tags.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect())
+            .unwrap_or_default();
+
+        let matches = |task: &Task| {
+            options.tag.as_ref().is_none_or(|tag| task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+                // With --completed, the overdue tasks wanted are completed ones
+                && (!options.overdue || task.is_overdue(today, options.completed))
+                && (!options.completed || task.completed)
         };
+        // Snoozing an overdue task counts from today, or it would stay overdue
+        let snoozed = |due: NaiveDate, days: i64| Duration::try_days(days)
+            .and_then(|delta| due.max(today).checked_add_signed(delta));
 
-        if tasks_to_show.is_empty() {
-            if urgent_only {
-                println!("No urgent tasks due within the next 3 days!");
-            } else {
-                println!("No tasks found!");
-            }
-            return;
+        // Check every new due date first, so an impossible one changes nothing
+        if let Some(days) = options.snooze
+            && let Some(task) = self.tasks.iter()
+                .filter(|task| matches(task))
+                .find(|task| task.due_date.is_some_and(|due| snoozed(due, days).is_none()))
+        {
+            eprintln!("Error: snoozing task #{} by {} days goes past the last supported date", task.id, days);
+            return Outcome::BadInput;
         }
 
-        tasks_to_show.sort_by(|a, b| {
-            let today = Local::now().date_naive();
-            match (a.due_date, b.due_date) {
-                (Some(ad), Some(bd)) => {
-                    let a_days = (ad - today).num_days();
-                    let b_days = (bd - today).num_days();
-                    a_days.cmp(&b_days)
-                }
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => std::cmp::Ordering::Equal,
+        let mut count = 0;
+        for task in self.tasks.iter_mut().filter(|task| matches(task)) {
+            if let Some(priority) = &options.set_priority {
+                task.priority = Some(priority.clone());
             }
-        });
-
-        let title = if urgent_only { "Urgent tasks:" } else { "Your tasks:" };
-        println!("{}\n", title);
-        
-        let today = Local::now().date_naive();
-        
-        for task in tasks_to_show {
-            let status = if task.completed { "✓" } else { " " };
-            let urgency_indicator = match task.due_date {
-                Some(due) => {
-                    let days_until = (due - today).num_days();
-                    if days_until < 0 {
-                        format!(" 🔴 OVERDUE by {} days", -days_until)
-                    } else {
-                        match days_until {
-                            0 => " 🟡 DUE TODAY".to_string(),
-                            1 => " 🟠 Due tomorrow".to_string(),
-                            2..=3 => format!(" 🟡 Due in {} days", days_until),
-                            4..=7 => format!(" (due {})", due.format("%m-%d")),
-                            _ => format!(" (due {})", due.format("%Y-%m-%d")),
-                        }
-                    }
-                }
-                None => String::new(),
-            };
-
+            if !new_tags.is_empty() {
+                task.tags.extend(new_tags.iter().cloned());
+                task.sort_tags();
+            }
+            if let Some(days) = options.snooze
+                && let Some(due) = task.due_date
+            {
+                task.due_date = snoozed(due, days);
+            }
+            count += 1;
+        }
 
-            // format priority
-            let priority_display = match &task.priority {
-                Some(p) => match p {
-                    Priority::High => "🔴 HIGH".to_string(),
-                    Priority::Medium => "🟡 MED".to_string(),
-                    Priority::Low => "🟢 LOW".to_string(),
-                },
-                None => "None".to_string(),
-            };
+        if count == 0 {
+            info!(self, "No tasks match the filters.");
+            return Outcome::Success;
+        }
 
-            // format tags (unwrap Option<Vec<String>> into a human string)
-            let tags_display = match &task.tags {
-                tags if !tags.is_empty() => tags.join(", "),
-                _ => "No tags".to_string(),
-            };
+        let outcome = self.save();
+        info!(self, "Updated {} tasks", count);
+        outcome
+    }
 
-            println!("[{}] {}: {}{}.\n  Priority: {}\n  Tags: {}\n", status, task.id, task.description, urgency_indicator, priority_display, tags_display);
+    pub fn list_tasks(&self, options: &ListOptions) -> Outcome {
+        let mut bounds = [None, None];
+        for (bound, input) in bounds.iter_mut().zip([&options.since, &options.until]) {
+            if let Some(input) = input {
+                let Some(date) = types::parse_date(input, self.today) else {
+                    eprintln!("Error: invalid date '{}'. Use YYYY-MM-DD, 'yesterday' or '-7d'.", input);
+                    return Outcome::BadInput;
+                };
+                *bound = Some(date);
+            }
         }
-    }
+        let [since, until] = bounds;
 
-    pub fn search_tasks(&self, keyword: &str) {
-        let matching_tasks: Vec<&Task> = self.tasks.iter()
-            .filter(|task| task.matches_keyword(keyword))
+        let urgent_only = options.urgent;
+        let mut tasks_to_show: Vec<&Task> = self.tasks.iter()
+            .filter(|task| !urgent_only || task.is_urgent(self.today, options.within, options.include_completed))
+            .filter(|task| completed_within(task, since, until))
+            .filter(|task| !options.active || !task.completed)
+            .filter(|task| options.label.is_none() || task.label == options.label)
+            .filter(|task| match options.max_time {
+                Some(budget) => task.estimate_minutes.is_some_and(|m| m <= budget),
+                None => true,
+            })
             .collect();
 
-        if matching_tasks.is_empty() {
-            println!("No tasks found matching '{}'", keyword);
-            return;
+        if options.count {
+            println!("{}", tasks_to_show.len());
+            return Outcome::Success;
         }
 
-        println!("Tasks matching '{}':", keyword);
-        for task in matching_tasks {
-            let status = if task.completed { "✓" } else { " " };
-            println!("[{}] {}: {}. Due - {}", 
-                status, 
-                task.id, 
-                task.description, 
-                task.due_date.map_or("No due date".to_string(), |d| d.to_string())
-            );
+        if tasks_to_show.is_empty() {
+            if options.format.is_some() || options.json_lines || options.tsv || options.ids_only || options.print0 {
+                // Templated output is for scripts; an empty list prints nothing
+            } else if urgent_only {
+                println!("No overdue tasks or tasks due within the next {} days!", options.within);
+            } else {
+                println!("No tasks found!");
+            }
+            return Outcome::Success;
         }
-    }
 
-    pub fn complete_task(&mut self, id: u32) {
-        match self.tasks.iter_mut().find(|task| task.id == id) {
-            Some(task) => {
-                task.complete();
-                self.storage.save_tasks(&self.tasks);
-                println!("Completed task #{}", id);
+        sort_tasks(&mut tasks_to_show, options.sort, options.reverse, self.today);
+        if options.json_lines {
+            return print_json_lines(tasks_to_show);
+        }
+        if options.tsv {
+            return print_tsv(tasks_to_show);
+        }
+        if options.ids_only || options.print0 {
+            return print_fields(&tasks_to_show, options.ids_only, options.print0);
+        }
+        let groups = group_tasks(&tasks_to_show, options.group_by);
+
+        if let Some(template) = &options.format {
+            for (header, tasks) in groups {
+                if let Some(header) = header {
+                    println!("{}:", header);
+                }
+                for task in tasks {
+                    println!("{}", render_template(template, task, self.symbols(), self.options.date_format.as_deref()));
+                }
             }
-            None => println!("Task #{} not found", id),
+            return Outcome::Success;
         }
-    }
 
-    pub fn remove_task(&mut self, id: u32) {
-        let initial_len = self.tasks.len();
+        if options.table {
+            let format = self.task_format(false, options.width);
+            for (header, tasks) in groups {
+                match header {
+                    Some(header) => println!("{}\n{}", self.heading(&format!("== {} ==", header)), task_table(&tasks, &format)),
+                    None => print!("{}", task_table(&tasks, &format)),
+                }
+            }
+            return Outcome::Success;
+        }
+
+        let title = if urgent_only {
+            format!("Urgent tasks (overdue or due within {} days):", options.within)
+        } else {
+            "Your tasks:".to_string()
+        };
+        println!("{}\n", self.heading(&title));
+
+        for (header, tasks) in groups {
+            if let Some(header) = header {
+                // Compact lines sit directly under their header, like table rows
+                let gap = if options.compact { "" } else { "\n" };
+                println!("{}{}", self.heading(&format!("== {} ==", header)), gap);
+            }
+            let format = TaskFormat {
+                absolute_dates: options.absolute_dates,
+                ..self.task_format(options.compact, options.width)
+            };
+            for task in tasks {
+                println!("{}", render::format_task_line(task, self.today, &format));
+            }
+        }
+        Outcome::Success
+    }
+
+    /// Markers for task output, plain ASCII under `--plain-emoji`
+    fn symbols(&self) -> &'static Symbols {
+        if self.options.plain_emoji { &render::PLAIN } else { &render::EMOJI }
+    }
+
+    /// Colors a title in the list's accent color, unless colors are off
+    fn heading(&self, text: &str) -> String {
+        render::heading(text, self.options.accent.filter(|_| self.options.color))
+    }
+
+    /// Layout options for `render::format_task_line` matching this app's settings
+    fn task_format(&self, compact: bool, width: Option<usize>) -> TaskFormat<'_> {
+        TaskFormat {
+            compact,
+            color: self.options.color,
+            width,
+            absolute_dates: false,
+            date_format: self.options.date_format.as_deref(),
+            strike_completed: self.options.strike_completed,
+            symbols: self.symbols(),
+        }
+    }
+
+    /// Sends desktop notifications for active tasks that are overdue or due today
+    pub fn notify(&self) -> Outcome {
+        let mut due: Vec<&Task> = self.tasks.iter()
+            .filter(|task| !task.completed && task.due_date.is_some_and(|date| date <= self.today))
+            .collect();
+        if due.is_empty() {
+            info!(self, "Nothing is due today, no notifications sent.");
+            return Outcome::Success;
+        }
+        due.sort_by_key(|task| (task.due_date, task.id));
+
+        let notifications = notify::build_notifications(&due, self.today);
+        for notification in &notifications {
+            if let Err(e) = notify::send(notification) {
+                eprintln!("Error: could not send notification: {}", e);
+                return Outcome::IoError;
+            }
+        }
+        info!(self, "Sent notifications for {} due tasks.", due.len());
+        Outcome::Success
+    }
+
+    /// Prints active dated tasks due in the next `days` days (today included),
+    /// grouped under one header per day
+    pub fn agenda(&self, days: i64) -> Outcome {
+        let mut upcoming: Vec<(NaiveDate, &Task)> = self.tasks.iter()
+            .filter(|task| !task.completed)
+            .filter_map(|task| task.due_date.map(|due| (due, task)))
+            .filter(|(due, _)| (0..days).contains(&(*due - self.today).num_days()))
+            .collect();
+
+        if upcoming.is_empty() {
+            println!("Nothing scheduled in the next {} days!", days);
+            return Outcome::Success;
+        }
+
+        upcoming.sort_by(|(a_due, a), (b_due, b)| {
+            a_due.cmp(b_due)
+                .then_with(|| b.priority.cmp(&a.priority))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let mut current_day = None;
+        for (due, task) in upcoming {
+            if current_day != Some(due) {
+                if current_day.is_some() {
+                    println!();
+                }
+                let header = match (due - self.today).num_days() {
+                    0 => "Today".to_string(),
+                    1 => "Tomorrow".to_string(),
+                    _ => format!("{} {}", due.format("%A"), render::format_date(due, self.options.date_format.as_deref(), "%Y-%m-%d")),
+                };
+                println!("{}:", header);
+                current_day = Some(due);
+            }
+            println!("  {}: {}", task.id, task.description);
+        }
+        Outcome::Success
+    }
+
+    /// Prints active tasks under headers for overdue, due today, upcoming in
+    /// the next week and undated, leaving out any section with no tasks
+    pub fn overview(&self) -> Outcome {
+        let mut active: Vec<&Task> = self.tasks.iter().filter(|task| !task.completed).collect();
+        sort_tasks(&mut active, SortKey::Due, false, self.today);
+
+        let in_section = |days: fn(Option<i64>) -> bool| -> Vec<&Task> {
+            active.iter()
+                .copied()
+                .filter(|task| days(task.due_date.map(|due| (due - self.today).num_days())))
+                .collect()
+        };
+        let sections = [
+            ("Overdue", in_section(|days| days.is_some_and(|d| d < 0))),
+            ("Due today", in_section(|days| days == Some(0))),
+            ("Upcoming", in_section(|days| days.is_some_and(|d| (1..=OVERVIEW_UPCOMING_DAYS).contains(&d)))),
+            ("No due date", in_section(|days| days.is_none())),
+        ];
+
+        if sections.iter().all(|(_, tasks)| tasks.is_empty()) {
+            println!("Nothing due in the next {} days, and no undated tasks!", OVERVIEW_UPCOMING_DAYS);
+            return Outcome::Success;
+        }
+
+        let format = self.task_format(true, render::terminal_width());
+        let mut first = true;
+        for (title, tasks) in sections.iter().filter(|(_, tasks)| !tasks.is_empty()) {
+            if !first {
+                println!();
+            }
+            first = false;
+            println!("{}", self.heading(&format!("== {} ({}) ==", title, tasks.len())));
+            for task in tasks {
+                println!("{}", render::format_task_line(task, self.today, &format));
+            }
+        }
+        Outcome::Success
+    }
+
+    /// The single most important active task: highest priority first, then the
+    /// soonest due date (undated tasks last), then the lowest ID
+    pub fn next_task(&self) -> Option<&Task> {
+        self.tasks.iter()
+            .filter(|task| !task.completed)
+            .min_by(|a, b| {
+                b.priority.cmp(&a.priority)
+                    .then_with(|| compare_due(a, b))
+                    .then_with(|| a.id.cmp(&b.id))
+            })
+    }
+
+    pub fn show_next(&self) -> Outcome {
+        match self.next_task() {
+            Some(task) => {
+                let priority_display = format_priority(&task.priority, self.options.color, self.symbols());
+                println!(
+                    "{}: {}\n  Due: {}\n  Priority: {}",
+                    task.id,
+                    task.description,
+                    task.due_date.map_or("No due date".to_string(), |due| render::format_date(due, self.options.date_format.as_deref(), "%Y-%m-%d")),
+                    priority_display
+                );
+                Outcome::Success
+            }
+            None => {
+                eprintln!("No active tasks!");
+                Outcome::NotFound
+            }
+        }
+    }
+
+    /// Prints one task in full, including when it was completed
+    pub fn show_task(&self, id: u32) -> Outcome {
+        let Some(task) = self.tasks.iter().find(|task| task.id == id) else {
+            eprintln!("Task #{} not found", id);
+            return Outcome::NotFound;
+        };
+
+        print!("{}", render::format_task_line(task, self.today, &self.task_format(false, render::terminal_width())));
+        if let Some(due) = task.due_date {
+            println!("  Due: {}", render::format_date(due, self.options.date_format.as_deref(), "%Y-%m-%d"));
+        }
+        if let Some(label) = task.label {
+            println!("  Label: {}", label.name());
+        }
+        if let Some(uid) = &task.uid {
+            println!("  UID: {}", uid);
+        }
+        if let Some(at) = task.completed_at {
+            let date = render::format_date(at.date_naive(), self.options.date_format.as_deref(), "%Y-%m-%d");
+            println!("  Completed: {} {} ({})", date, at.format("%H:%M"), render::humanize_since(at, self.now()));
+        }
+        Outcome::Success
+    }
+
+    pub fn tasks_due_on(&self, date: NaiveDate) -> Vec<&Task> {
+        self.tasks.iter()
+            .filter(|task| task.due_date == Some(date))
+            .collect()
+    }
+
+    pub fn show_due_on(&self, date_str: &str) -> Outcome {
+        let Some(date) = types::parse_date(date_str, self.today) else {
+            eprintln!("Error: invalid date '{}'. Use YYYY-MM-DD, 'tomorrow', '+3d' or a weekday.", date_str);
+            return Outcome::BadInput;
+        };
+
+        let due = self.tasks_due_on(date);
+        let shown = render::format_date(date, self.options.date_format.as_deref(), "%Y-%m-%d");
+        if due.is_empty() {
+            println!("No tasks due on {}", shown);
+            return Outcome::Success;
+        }
+
+        println!("Tasks due on {}:", shown);
+        for task in due {
+            let status = if task.completed { self.symbols().done } else { " " };
+            println!("[{}] {}: {}", status, task.id, task.description);
+        }
+        Outcome::Success
+    }
+
+    /// Prints `id<TAB>description` for each active task, for shell completion
+    pub fn print_completion_ids(&self) -> Outcome {
+        const MAX_DESCRIPTION: usize = 40;
+        for task in self.tasks.iter().filter(|task| !task.completed) {
+            let mut description: String = task.description.chars().take(MAX_DESCRIPTION).collect();
+            if task.description.chars().count() > MAX_DESCRIPTION {
+                description.push('…');
+            }
+            println!("{}\t{}", task.id, description);
+        }
+        Outcome::Success
+    }
+
+    pub fn search_tasks(&self, keyword: &str, options: &SearchOptions) -> Outcome {
+        let pattern = if options.regex {
+            match RegexBuilder::new(keyword).case_insensitive(!options.case_sensitive).build() {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    eprintln!("Error: invalid regular expression '{}': {}", keyword, e);
+                    return Outcome::BadInput;
+                }
+            }
+        } else {
+            None
+        };
+        let matches = |task: &&Task| !(options.active && task.completed) && match &pattern {
+            Some(pattern) => task.matches_regex(pattern),
+            None if options.case_sensitive => task.matches_keyword_cased(keyword),
+            None => task.matches_keyword(keyword),
+        };
+
+        let mut matching_tasks: Vec<&Task> = self.tasks.iter().filter(matches).collect();
+
+        let archived = if options.include_archived { self.storage.load_archive() } else { Vec::new() };
+        let mut matching_archived: Vec<&Task> = archived.iter().filter(matches).collect();
+
+        if let Some(sort) = options.sort {
+            sort_tasks(&mut matching_tasks, sort, false, self.today);
+            sort_tasks(&mut matching_archived, sort, false, self.today);
+        }
+
+        if options.ids_only || options.print0 {
+            return print_fields(&matching_tasks, options.ids_only, options.print0);
+        }
+        if matching_tasks.is_empty() && matching_archived.is_empty() {
+            println!("No tasks found matching '{}'", keyword);
+            return Outcome::Success;
+        }
+
+        let format = self.task_format(false, render::terminal_width());
+        println!("{}\n", self.heading(&format!("Tasks matching '{}':", keyword)));
+        for task in matching_tasks {
+            println!("{}", render::format_task_line(task, self.today, &format));
+        }
+        if !matching_archived.is_empty() {
+            println!("{}\n", self.heading("== Archived =="));
+            for task in matching_archived {
+                println!("{}", render::format_task_line(task, self.today, &format));
+            }
+        }
+        Outcome::Success
+    }
+
+    /// Prints the tasks matching a `find` query, see `query::parse` for the syntax
+    pub fn find(&self, query: &str) -> Outcome {
+        let parsed = match query::parse(query) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("Error: invalid query: {}", e);
+                return Outcome::BadInput;
+            }
+        };
+
+        let matching: Vec<&Task> = self.tasks.iter()
+            .filter(|task| parsed.matches(task, self.today))
+            .collect();
+        if matching.is_empty() {
+            println!("No tasks match '{}'", query);
+            return Outcome::Success;
+        }
+
+        let format = self.task_format(false, render::terminal_width());
+        println!("{}\n", self.heading(&format!("Tasks matching '{}':", query)));
+        for task in matching {
+            println!("{}", render::format_task_line(task, self.today, &format));
+        }
+        Outcome::Success
+    }
+
+    /// Prints how many tasks fall under each tag or priority, using the same
+    /// groups as `list --group-by`. A task with several tags counts once per tag.
+    pub fn count_by(&self, dimension: GroupBy, active_only: bool) -> Outcome {
+        let tasks: Vec<&Task> = self.tasks.iter()
+            .filter(|task| !active_only || !task.completed)
+            .collect();
+        if tasks.is_empty() {
+            println!("No tasks found!");
+            return Outcome::Success;
+        }
+
+        let header = match dimension {
+            GroupBy::Tag => "Tag",
+            GroupBy::Priority => "Priority",
+        };
+        let rows: Vec<Vec<String>> = group_tasks(&tasks, Some(dimension)).into_iter()
+            .map(|(group, members)| vec![group.unwrap_or_default(), members.len().to_string()])
+            .collect();
+        print!("{}", render::table(&[header, "Tasks"], &rows));
+        Outcome::Success
+    }
+
+    pub fn stats(&self) -> Outcome {
+        let total = self.tasks.len();
+        let active: Vec<&Task> = self.tasks.iter().filter(|task| !task.completed).collect();
+        let overdue = active.iter().filter(|task| task.is_overdue(self.today, false)).count();
+        let remaining: u32 = active.iter().filter_map(|task| task.estimate_minutes).sum();
+        let unestimated = active.iter().filter(|task| task.estimate_minutes.is_none()).count();
+
+        println!("Tasks: {} total, {} active, {} completed", total, active.len(), total - active.len());
+        println!("Overdue: {}", overdue);
+        if unestimated == 0 {
+            println!("Remaining estimate: {}", types::format_duration(remaining));
+        } else {
+            println!(
+                "Remaining estimate: {} ({} active tasks have no estimate)",
+                types::format_duration(remaining),
+                unestimated
+            );
+        }
+        Outcome::Success
+    }
+
+    /// Summarizes tasks completed so far this week or month, day by day
+    ///
+    /// # Arguments
+    /// * `period` - The span to report on, ending today
+    /// * `streak` - Also print how many days in a row ending today had a completion
+    pub fn report(&self, period: ReportPeriod, streak: bool) -> Outcome {
+        let start = period.start(self.today);
+        let completion_days: Vec<NaiveDate> = self.tasks.iter()
+            .filter_map(|task| task.completed_at)
+            .map(|at| at.date_naive())
+            .collect();
+
+        let days = completions_per_day(&completion_days, start, self.today);
+        let total: usize = days.iter().map(|(_, count)| count).sum();
+
+        let date_format = self.options.date_format.as_deref();
+        println!("Completed {} to {}: {} tasks",
+            render::format_date(start, date_format, "%Y-%m-%d"), render::format_date(self.today, date_format, "%Y-%m-%d"), total);
+        for (day, count) in &days {
+            println!("  {} {}: {}", day.format("%a"), render::format_date(*day, date_format, "%Y-%m-%d"), count);
+        }
+        println!("Average: {:.1} per day", total as f64 / days.len() as f64);
+        if let Some((task, at)) = self.tasks.iter()
+            .filter_map(|task| task.completed_at.map(|at| (task, at)))
+            .filter(|(_, at)| at.date_naive() >= start)
+            .max_by_key(|(_, at)| *at)
+        {
+            println!("Last: #{} {} (completed {})", task.id, task.description, render::humanize_since(at, self.now()));
+        }
+
+        if streak {
+            let length = completion_streak(&completion_days, self.today);
+            println!("Streak: {} day{}", length, if length == 1 { "" } else { "s" });
+        }
+        Outcome::Success
+    }
+
+    /// Writes every task in `format` (or as JSON lines) to `output`, or to
+    /// stdout when no file is given
+    pub fn export(&self, format: ExportFormat, json_lines: bool, output: Option<&Path>, fields: &[ExportField]) -> Outcome {
+        if !fields.is_empty() && format != ExportFormat::Csv {
+            eprintln!("Error: --fields only applies to CSV; add --format csv");
+            return Outcome::BadInput;
+        }
+
+        let destination: Box<dyn Write> = match output {
+            Some(path) => match export::create_output(path) {
+                Ok(file) => Box::new(file),
+                Err(e) => {
+                    eprintln!("Error: could not create {}: {}", path.display(), e);
+                    return Outcome::IoError;
+                }
+            },
+            None => Box::new(io::stdout().lock()),
+        };
+        let mut writer = BufWriter::new(destination);
+
+        let written = if json_lines {
+            export::write_json_lines(&self.tasks, &mut writer)
+        } else {
+            match format {
+                ExportFormat::Json => export::to_json(&self.tasks)
+                    .map_err(io::Error::from)
+                    .and_then(|json| writeln!(writer, "{}", json)),
+                ExportFormat::Ics => write!(writer, "{}", export::to_ics(&self.tasks)),
+                ExportFormat::TodoTxt => write!(writer, "{}", export::to_todo_txt(&self.tasks)),
+                ExportFormat::Csv => {
+                    let fields = if fields.is_empty() { &export::DEFAULT_CSV_FIELDS[..] } else { fields };
+                    write!(writer, "{}", export::to_csv(&self.tasks, fields))
+                }
+            }
+        };
+
+        match written.and_then(|()| writer.flush()) {
+            Ok(()) => {
+                if let Some(path) = output {
+                    info!(self, "Exported {} tasks to {}", self.tasks.len(), path.display());
+                }
+                Outcome::Success
+            }
+            Err(e) => {
+                let target = output.map(|path| path.display().to_string()).unwrap_or_else(|| "tasks".to_string());
+                eprintln!("Error: could not write {}: {}", target, e);
+                Outcome::IoError
+            }
+        }
+    }
+
+    /// Appends tasks from a file, giving each one a fresh ID. With
+    /// `skip_duplicates`, a task whose description (ignoring case) and due date
+    /// match one already in the list, or earlier in the file, is left out.
+    pub fn import(&mut self, path: &str, format: ImportFormat, skip_duplicates: bool) -> Outcome {
+        let incoming = match read_import(path, format) {
+            Ok(tasks) => tasks,
+            Err(outcome) => return outcome,
+        };
+
+        let count = incoming.len();
+        let mut skipped = 0;
+        for (done, mut task) in (1..).zip(incoming) {
+            if skip_duplicates && self.tasks.iter().any(|existing| is_duplicate(existing, &task)) {
+                skipped += 1;
+                continue;
+            }
+            task.sort_tags();
+            // Re-importing an export would otherwise bring back uids already in use
+            if task.uid.is_none() || self.tasks.iter().any(|existing| existing.uid == task.uid) {
+                task.uid = Some(unique_uid(&self.tasks));
+            }
+            task.id = self.next_id;
+            self.next_id += 1;
+            self.tasks.push(task);
+            if count > IMPORT_PROGRESS_THRESHOLD && done % IMPORT_PROGRESS_STEP == 0 && done < count {
+                info!(self, "Imported {}/{}", done, count);
+            }
+        }
+
+        let outcome = self.save();
+        if skip_duplicates {
+            info!(self, "Imported {} tasks from {}, skipped {} duplicates", count - skipped, path, skipped);
+        } else {
+            info!(self, "Imported {} tasks from {}", count, path);
+        }
+        outcome
+    }
+
+    /// Imports tasks from a file, reconciling them with the list by ID
+    ///
+    /// A task whose ID is already in the list replaces it, unless the local
+    /// copy was completed more recently; other tasks are appended with their
+    /// own IDs. Only JSON carries IDs, so other formats are refused.
+    pub fn import_merge(&mut self, path: &str, format: ImportFormat) -> Outcome {
+        // todo.txt lines have no IDs to match on; they'd all parse as ID 0
+        // and replace one another
+        if format != ImportFormat::Json {
+            eprintln!("Error: --merge matches tasks by ID, which todo.txt files don't have");
+            eprintln!("Import without --merge to append them as new tasks.");
+            return Outcome::BadInput;
+        }
+        let incoming = match read_import(path, format) {
+            Ok(tasks) => tasks,
+            Err(outcome) => return outcome,
+        };
+        // An appended task moves next_id past its own ID, which must leave room
+        // for one more; check them all before changing anything
+        if let Some(task) = incoming.iter()
+            .find(|task| task.id.checked_add(1).is_none() && !self.tasks.iter().any(|existing| existing.id == task.id))
+        {
+            eprintln!("Error: task ID {} in {} is too large to add", task.id, path);
+            return Outcome::BadInput;
+        }
+
+        let (mut updated, mut added, mut kept) = (0, 0, 0);
+        for mut task in incoming {
+            task.sort_tags();
+            let Some(index) = self.tasks.iter().position(|existing| existing.id == task.id) else {
+                if task.uid.is_none() || self.tasks.iter().any(|existing| existing.uid == task.uid) {
+                    task.uid = Some(unique_uid(&self.tasks));
+                }
+                self.next_id = self.next_id.max(task.id.checked_add(1).expect("checked above"));
+                self.tasks.push(task);
+                added += 1;
+                continue;
+            };
+
+            let local = &self.tasks[index];
+            if local.completed_at > task.completed_at {
+                kept += 1;
+                continue;
+            }
+            if task.uid.is_none() || self.tasks.iter().any(|existing| existing.id != task.id && existing.uid == task.uid) {
+                task.uid = local.uid.clone();
+            }
+            self.tasks[index] = task;
+            updated += 1;
+        }
+
+        let outcome = self.save();
+        info!(self, "Merged {}: updated {}, added {}, kept {} newer local tasks", path, updated, added, kept);
+        outcome
+    }
+
+    /// Commits the data directory to git, then pulls and pushes its remote
+    pub fn sync(&self) -> Outcome {
+        let Some(directory) = self.storage.path().parent() else {
+            eprintln!("Error: could not find the data directory");
+            return Outcome::IoError;
+        };
+        let message = format!("Sync tasks {}", Local::now().format("%Y-%m-%d %H:%M"));
+        match sync::sync(directory, &message) {
+            Ok(committed) => {
+                if committed {
+                    info!(self, "Committed local changes");
+                }
+                info!(self, "Synced {}", directory.display());
+                Outcome::Success
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                Outcome::IoError
+            }
+        }
+    }
+
+    /// Moves every completed task into the archive file
+    pub fn archive_completed(&mut self) -> Outcome {
+        let (completed, active): (Vec<Task>, Vec<Task>) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|task| task.completed);
+
+        if completed.is_empty() {
+            self.tasks = active;
+            info!(self, "No completed tasks to archive.");
+            return Outcome::Success;
+        }
+
+        // Write the archive first so a failure can't lose the completed tasks
+        if !self.storage.append_to_archive(&completed) {
+            self.tasks = completed.into_iter().chain(active).collect();
+            return Outcome::IoError;
+        }
+
+        self.tasks = active;
+        let outcome = self.save();
+        info!(self, "Archived {} completed tasks.", completed.len());
+        outcome
+    }
+
+    /// Archives, or with `delete` removes, tasks completed more than `older_than`
+    /// days ago. Tasks completed before completion times were recorded are kept.
+    pub fn cleanup(&mut self, older_than: i64, delete: bool) -> Outcome {
+        let today = self.today;
+        let (old, kept): (Vec<Task>, Vec<Task>) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|task| task.completed && task.completed_at
+                .is_some_and(|at| (today - at.date_naive()).num_days() > older_than));
+
+        if old.is_empty() {
+            self.tasks = kept;
+            info!(self, "No tasks completed more than {} days ago.", older_than);
+            return Outcome::Success;
+        }
+
+        // Write the archive first so a failure can't lose the old tasks
+        if !delete && !self.storage.append_to_archive(&old) {
+            self.tasks = old.into_iter().chain(kept).collect();
+            return Outcome::IoError;
+        }
+
+        self.tasks = kept;
+        let outcome = self.save();
+        let action = if delete { "Removed" } else { "Archived" };
+        info!(self, "{} {} tasks completed more than {} days ago.", action, old.len(), older_than);
+        outcome
+    }
+
+    /// Lowercases every task's tags so case variants such as `Work` and `work`
+    /// become one tag, dropping repeats within a task
+    pub fn normalize_tags(&mut self) -> Outcome {
+        let (changed, variants) = merge_tag_variants(&mut self.tasks);
+        if changed == 0 {
+            info!(self, "Tags are already normalized.");
+            return Outcome::Success;
+        }
+        let outcome = self.save();
+        for (tag, spellings) in &variants {
+            let spellings: Vec<&str> = spellings.iter().map(String::as_str).collect();
+            info!(self, "Merged {} into {}", spellings.join(", "), tag);
+        }
+        info!(self, "Normalized tags on {} tasks.", changed);
+        outcome
+    }
+
+    /// IDs of the first or last `count` active tasks in the order `list` shows
+    /// them with `sort`, for `--first` and `--last`
+    pub fn select_ids(&self, first: Option<usize>, last: Option<usize>, sort: SortKey) -> Vec<u32> {
+        let mut active: Vec<&Task> = self.tasks.iter().filter(|task| !task.completed).collect();
+        sort_tasks(&mut active, sort, false, self.today);
+        let selected = match (first, last) {
+            (Some(count), _) => &active[..count.min(active.len())],
+            (None, Some(count)) => &active[active.len().saturating_sub(count)..],
+            (None, None) => &[],
+        };
+        if selected.is_empty() {
+            info!(self, "No active tasks to select.");
+        }
+        selected.iter().map(|task| task.id).collect()
+    }
+
+    /// The date due dates are compared against for this run
+    pub fn today(&self) -> NaiveDate {
+        self.today
+    }
+
+    /// The highest task ID in use, or 0 for an empty list
+    pub fn highest_id(&self) -> u32 {
+        self.tasks.iter().map(|task| task.id).max().unwrap_or(0)
+    }
+
+    pub fn overdue_count(&self) -> usize {
+        self.tasks.iter().filter(|task| task.is_overdue(self.today, false)).count()
+    }
+
+    /// One-line summary such as "⚠ 3 tasks overdue, 2 due today" shown before
+    /// a command runs, or `None` when nothing is overdue or due today
+    pub fn reminder_banner(&self) -> Option<String> {
+        let overdue = self.overdue_count();
+        let due_today = self.tasks_due_on(self.today).iter().filter(|task| !task.completed).count();
+        let plural = |count: usize| if count == 1 { "task" } else { "tasks" };
+
+        let summary = match (overdue, due_today) {
+            (0, 0) => return None,
+            (overdue, 0) => format!("{} {} overdue", overdue, plural(overdue)),
+            (0, today) => format!("{} {} due today", today, plural(today)),
+            (overdue, today) => format!("{} {} overdue, {} due today", overdue, plural(overdue), today),
+        };
+        let banner = format!("{} {}", self.symbols().warning, summary);
+        Some(if overdue > 0 { render::alert(&banner, self.options.color) } else { banner })
+    }
+
+    /// Marks every overdue active task complete, saving once
+    pub fn complete_overdue(&mut self) -> Outcome {
+        let now = self.now();
+        let today = self.today;
+        let mut count = 0;
+        for task in self.tasks.iter_mut().filter(|task| task.is_overdue(today, false)) {
+            task.complete(now);
+            count += 1;
+        }
+
+        if count == 0 {
+            info!(self, "No overdue tasks.");
+            return Outcome::Success;
+        }
+
+        let outcome = self.save();
+        info!(self, "Completed {} overdue tasks", count);
+        outcome
+    }
+
+    pub fn complete_task(&mut self, id: u32) -> Outcome {
+        let now = self.now();
+        match self.tasks.iter_mut().find(|task| task.id == id) {
+            Some(task) => {
+                task.complete(now);
+                let event = JournalEvent::Upsert(task.clone());
+                let outcome = self.record(event);
+                info!(self, "Completed task #{}", id);
+                outcome
+            }
+            None => {
+                eprintln!("Task #{} not found", id);
+                Outcome::NotFound
+            }
+        }
+    }
+
+    /// Completes the active task whose description contains `text`. When
+    /// several match, a menu asks which to complete; without a terminal to
+    /// ask on, the matches are listed and nothing is completed.
+    pub fn complete_matching(&mut self, text: &str) -> Outcome {
+        let candidates: Vec<&Task> = self.tasks.iter()
+            .filter(|task| !task.completed && task.matches_keyword(text))
+            .collect();
+
+        let ids = match candidates.as_slice() {
+            [] => {
+                eprintln!("No active task matches '{}'", text);
+                return Outcome::NotFound;
+            }
+            [task] => vec![task.id],
+            _ if !prompt::is_interactive() => {
+                eprintln!("Error: '{}' matches {} tasks; complete one by ID:", text, candidates.len());
+                for task in &candidates {
+                    eprintln!("  #{} {}", task.id, task.description);
+                }
+                return Outcome::BadInput;
+            }
+            _ => {
+                println!("'{}' matches {} tasks:", text, candidates.len());
+                let menu: Vec<Task> = candidates.into_iter().cloned().collect();
+                match self.without_lock(|| prompt::select_from(&menu.iter().collect::<Vec<_>>())) {
+                    Some(ids) => ids,
+                    None => {
+                        eprintln!("Cancelled, no tasks were completed");
+                        return Outcome::BadInput;
+                    }
+                }
+            }
+        };
+
+        self.begin_batch();
+        for id in ids {
+            self.complete_task(id);
+        }
+        self.commit_batch()
+    }
+
+    /// Pins or unpins a task, keeping it above unpinned tasks in `list`
+    pub fn set_pinned(&mut self, id: u32, pinned: bool) -> Outcome {
+        match self.tasks.iter_mut().find(|task| task.id == id) {
+            Some(task) => {
+                task.pinned = pinned;
+                let event = JournalEvent::Upsert(task.clone());
+                let outcome = self.record(event);
+                info!(self, "{} task #{}", if pinned { "Pinned" } else { "Unpinned" }, id);
+                outcome
+            }
+            None => {
+                eprintln!("Task #{} not found", id);
+                Outcome::NotFound
+            }
+        }
+    }
+
+    pub fn remove_task(&mut self, id: u32) -> Outcome {
+        let initial_len = self.tasks.len();
         self.tasks.retain(|task| task.id != id);
         
         if self.tasks.len() < initial_len {
-            self.storage.save_tasks(&self.tasks);
-            println!("Removed task #{}", id);
+            let outcome = self.record(JournalEvent::Remove(id));
+            info!(self, "Removed task #{}", id);
+            outcome
         } else {
-            println!("Task #{} not found", id);
+            eprintln!("Task #{} not found", id);
+            Outcome::NotFound
         }
     }
 
-    pub fn remove_all_tasks(&mut self) {
+    pub fn remove_all_tasks(&mut self) -> Outcome {
         self.tasks.clear();
         self.next_id = 1;
-        self.storage.save_tasks(&self.tasks);
-        println!("All tasks have been removed.");
+        let outcome = self.save();
+        info!(self, "All tasks have been removed.");
+        outcome
     }
-}
\ No newline at end of file
+
+    /// Gives every task whose ID was already used earlier in the list a fresh,
+    /// unused ID, numbered sequentially after the current highest ID.
+    ///
+    /// # Returns
+    /// * `Vec<(u32, u32)>` - The `(old, new)` ID pairs that were reassigned
+    fn repair_duplicate_ids(&mut self) -> Vec<(u32, u32)> {
+        let mut seen = std::collections::HashSet::new();
+        let mut reassigned = Vec::new();
+
+        for task in self.tasks.iter_mut() {
+            if !seen.insert(task.id) {
+                reassigned.push((task.id, self.next_id));
+                task.id = self.next_id;
+                seen.insert(task.id);
+                self.next_id += 1;
+            }
+        }
+        reassigned
+    }
+
+    /// Rewrites the data file in the current format, keeping a backup of the original
+    pub fn migrate(&mut self) -> Outcome {
+        let diagnosis = self.storage.diagnose();
+        if !diagnosis.exists {
+            info!(self, "No data file yet, nothing to migrate");
+            return Outcome::Success;
+        }
+        // Saving after a failed load would replace the file with an empty list
+        let report = match self.storage.inspect_format() {
+            Some(report) if diagnosis.integrity_ok => report,
+            _ => {
+                eprintln!("Error: the data file could not be read; run `todo doctor` for details");
+                return Outcome::IoError;
+            }
+        };
+
+        if report.version > storage::DATA_VERSION {
+            eprintln!(
+                "Error: the data file uses format {}, newer than this build supports ({})",
+                report.version, storage::DATA_VERSION
+            );
+            return Outcome::BadInput;
+        }
+        if report.is_current() {
+            info!(self, "Data file is already up to date (format {})", report.version);
+            return Outcome::Success;
+        }
+
+        match self.storage.backup(&format!("v{}", report.version)) {
+            Ok(path) => info!(self, "Backed up the original to {}", path.display()),
+            Err(e) => {
+                eprintln!("Error: could not back up the data file, nothing was changed: {}", e);
+                return Outcome::IoError;
+            }
+        }
+
+        let outcome = self.save();
+        if outcome.is_success() {
+            if report.version != storage::DATA_VERSION {
+                info!(self, "Upgraded format {} to {}", report.version, storage::DATA_VERSION);
+            }
+            for (field, count) in &report.missing_fields {
+                info!(self, "Added `{}` to {} task{}", field, count, if *count == 1 { "" } else { "s" });
+            }
+        }
+        outcome
+    }
+
+    pub fn doctor(&mut self, repair: bool) -> Outcome {
+        let report = self.storage.diagnose();
+        let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+
+        println!("Data file: {}", report.path);
+        println!("  Exists: {}", yes_no(report.exists));
+        if report.exists {
+            println!("  Size: {} bytes", report.size.unwrap_or(0));
+            println!("  Readable: {}", yes_no(report.parses));
+            println!("  Integrity check: {}", if report.integrity_ok { "passed" } else { "FAILED" });
+            if report.duplicate_ids.is_empty() {
+                println!("  Duplicate IDs: none");
+            } else {
+                let ids: Vec<String> = report.duplicate_ids.iter().map(|id| id.to_string()).collect();
+                println!("  Duplicate IDs: {}", ids.join(", "));
+            }
+            match report.permissions {
+                Some(mode) => println!("  Permissions: {:o}", mode),
+                None => println!("  Permissions: unavailable"),
+            }
+        }
+
+        if repair && !report.duplicate_ids.is_empty() {
+            // A file that fails the integrity check loads as an empty list, so
+            // saving the repair would wipe every task in it
+            if !report.integrity_ok {
+                eprintln!("Error: not repairing, the data file failed its integrity check and saving would lose its tasks");
+                eprintln!("Fix or restore the file first, then run `todo doctor --repair` again.");
+                return Outcome::IoError;
+            }
+            let reassigned = self.repair_duplicate_ids();
+            let outcome = self.save();
+            println!("\nRepaired duplicate IDs:");
+            for (old, new) in reassigned {
+                println!("  #{} -> #{}", old, new);
+            }
+            return outcome;
+        }
+
+        if report.is_healthy() {
+            println!("\nNo problems found.");
+            Outcome::Success
+        } else {
+            println!("\nProblems were found with the data file.");
+            Outcome::IoError
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task_due(id: u32, description: &str, due: Option<NaiveDate>) -> Task {
+        Task::new(id, description.to_string(), Some(Priority::High), vec!["work".to_string()], due)
+    }
+
+    #[test]
+    fn render_template_fills_placeholders() {
+        let task = task_due(3, "Write report", NaiveDate::from_ymd_opt(2025, 6, 1));
+        let line = render_template("{id}\t{desc} ({due}) {priority} {tags} [{status}] {other}", &task, &render::PLAIN, None);
+        assert_eq!(line, "3\tWrite report (2025-06-01) HIGH work [ ] {other}");
+    }
+
+    #[test]
+    fn render_template_due_follows_date_format() {
+        let task = task_due(3, "Write report", NaiveDate::from_ymd_opt(2025, 6, 1));
+        assert_eq!(render_template("{due}", &task, &render::PLAIN, Some("%d/%m/%Y")), "01/06/2025");
+        let undated = task_due(4, "Someday", None);
+        assert_eq!(render_template("[{due}]", &undated, &render::PLAIN, Some("%d/%m/%Y")), "[]");
+    }
+
+    /// An empty directory for one test's files
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let directory = std::env::temp_dir().join(format!("todo-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    /// A fresh directory holding a legacy data file whose one task has no uid
+    fn legacy_data_dir(name: &str) -> std::path::PathBuf {
+        let directory = scratch_dir(name);
+        let mut task = serde_json::to_value(task_due(1, "Old task", None)).unwrap();
+        task.as_object_mut().unwrap().remove("uid");
+        std::fs::write(directory.join(".todo_data.json"), serde_json::json!([task]).to_string()).unwrap();
+        directory
+    }
+
+    #[test]
+    fn loading_for_a_read_only_command_leaves_the_file_alone() {
+        let directory = legacy_data_dir("read-only");
+        let before = std::fs::read_to_string(directory.join(".todo_data.json")).unwrap();
+        let storage = storage::JsonStorage::new(&directory, storage::StorageOptions::default());
+        let app = TodoApp::new(AppOptions::default(), Box::new(storage));
+        assert!(app.tasks[0].uid.is_some());
+        assert_eq!(std::fs::read_to_string(directory.join(".todo_data.json")).unwrap(), before);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn loading_for_a_mutating_command_saves_new_uids() {
+        let directory = legacy_data_dir("mutating");
+        let storage = storage::JsonStorage::new(&directory, storage::StorageOptions::default());
+        let app = TodoApp::new(AppOptions { save_on_load: true, ..AppOptions::default() }, Box::new(storage));
+        let (_, saved) = storage::parse_data(&std::fs::read_to_string(directory.join(".todo_data.json")).unwrap()).unwrap();
+        assert_eq!(saved[0].uid, app.tasks[0].uid);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// A fresh directory holding `tasks`, and an app opened on it
+    fn app_with(name: &str, tasks: &[Task]) -> (std::path::PathBuf, TodoApp) {
+        let directory = scratch_dir(name);
+        let storage = storage::JsonStorage::new(&directory, storage::StorageOptions::default());
+        storage.save_tasks(tasks);
+        (directory.clone(), TodoApp::new(AppOptions { quiet: true, ..AppOptions::default() }, Box::new(storage)))
+    }
+
+    #[test]
+    fn merge_marks_a_task_completed_elsewhere_complete() {
+        let local = [task_due(1, "Walk dog", None), task_due(2, "Buy milk", None)];
+        let (directory, mut app) = app_with("merge-json", &local);
+        let mut remote = local.clone();
+        remote[1].complete(Local::now().fixed_offset());
+        let file = directory.join("remote.json");
+        std::fs::write(&file, serde_json::to_string(&remote).unwrap()).unwrap();
+
+        assert_eq!(app.import_merge(file.to_str().unwrap(), ImportFormat::Json), Outcome::Success);
+        assert_eq!(app.tasks.len(), 2);
+        assert!(!app.tasks[0].completed);
+        assert!(app.tasks[1].completed);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn merge_refuses_todo_txt_instead_of_losing_tasks() {
+        let (directory, mut app) = app_with("merge-todotxt", &[task_due(1, "Walk dog", None)]);
+        let file = directory.join("todo.txt");
+        std::fs::write(&file, "Call mom\nx Pay rent\n(A) File taxes +home\n").unwrap();
+
+        assert_eq!(app.import_merge(file.to_str().unwrap(), ImportFormat::TodoTxt), Outcome::BadInput);
+        assert_eq!(app.tasks.len(), 1);
+        assert_eq!(app.tasks[0].description, "Walk dog");
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn next_task_prefers_priority_over_an_earlier_due_date() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 11).unwrap();
+        let mut soon_low = task_due(1, "Water plants", Some(today));
+        soon_low.priority = Some(Priority::Low);
+        let later_high = task_due(2, "File taxes", today.checked_add_days(chrono::Days::new(30)));
+        let mut done_high = task_due(3, "Pay rent", Some(today));
+        done_high.completed = true;
+        let (directory, app) = app_with("next", &[soon_low, later_high, done_high]);
+
+        assert_eq!(app.next_task().map(|task| task.id), Some(2));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn complete_overdue_leaves_future_tasks_active() {
+        let today = Local::now().date_naive();
+        let tasks = [
+            task_due(1, "Pay rent", today.checked_sub_days(chrono::Days::new(1))),
+            task_due(2, "Call mom", today.checked_sub_days(chrono::Days::new(5))),
+            task_due(3, "File taxes", today.checked_add_days(chrono::Days::new(3))),
+        ];
+        let (directory, mut app) = app_with("overdue", &tasks);
+
+        assert_eq!(app.complete_overdue(), Outcome::Success);
+        let completed: Vec<bool> = app.tasks.iter().map(|task| task.completed).collect();
+        assert_eq!(completed, [true, true, false]);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn completed_within_includes_both_bounds() {
+        let date = |day| NaiveDate::from_ymd_opt(2025, 6, day).unwrap();
+        let completed_on = |day: u32| {
+            let mut task = task_due(1, "Walk dog", None);
+            task.complete(DateTime::parse_from_rfc3339(&format!("2025-06-{:02}T23:30:00+02:00", day)).unwrap());
+            task
+        };
+        let (since, until) = (Some(date(10)), Some(date(12)));
+
+        assert!(!completed_within(&completed_on(9), since, until));
+        assert!(completed_within(&completed_on(10), since, until));
+        assert!(completed_within(&completed_on(12), since, until));
+        assert!(!completed_within(&completed_on(13), since, until));
+        assert!(completed_within(&completed_on(1), None, until));
+        assert!(!completed_within(&task_due(2, "Buy milk", None), since, None));
+        assert!(completed_within(&task_due(2, "Buy milk", None), None, None));
+    }
+
+    #[test]
+    fn report_counts_completions_per_day_and_the_streak() {
+        let date = |day| NaiveDate::from_ymd_opt(2025, 6, day).unwrap();
+        let done = [date(9), date(10), date(10), date(11), date(3)];
+
+        let days = completions_per_day(&done, date(9), date(12));
+        assert_eq!(days, [(date(9), 1), (date(10), 2), (date(11), 1), (date(12), 0)]);
+        // Nothing yet on the 12th, so the streak runs back from the 11th
+        assert_eq!(completion_streak(&done, date(12)), 3);
+        assert_eq!(completion_streak(&done, date(13)), 0);
+    }
+
+    /// JSON storage that counts calls to `save_tasks`
+    struct CountingStorage {
+        inner: storage::JsonStorage,
+        saves: std::rc::Rc<std::cell::Cell<usize>>,
+    }
+
+    impl StorageBackend for CountingStorage {
+        fn path(&self) -> &Path {
+            self.inner.path()
+        }
+
+        fn load_tasks(&self) -> Vec<Task> {
+            self.inner.load_tasks()
+        }
+
+        fn save_tasks(&self, tasks: &[Task]) -> bool {
+            self.saves.set(self.saves.get() + 1);
+            self.inner.save_tasks(tasks)
+        }
+
+        fn load_archive(&self) -> Vec<Task> {
+            self.inner.load_archive()
+        }
+
+        fn append_to_archive(&self, tasks: &[Task]) -> bool {
+            self.inner.append_to_archive(tasks)
+        }
+
+        fn diagnose(&self) -> storage::Diagnosis {
+            self.inner.diagnose()
+        }
+
+        fn inspect_format(&self) -> Option<storage::FormatReport> {
+            self.inner.inspect_format()
+        }
+
+        fn stored_version(&self) -> Option<u32> {
+            self.inner.stored_version()
+        }
+
+        fn lock(&self) -> Option<File> {
+            self.inner.lock()
+        }
+    }
+
+    #[test]
+    fn a_batch_of_adds_saves_once() {
+        let directory = scratch_dir("batch");
+        let saves = std::rc::Rc::new(std::cell::Cell::new(0));
+        let storage = CountingStorage {
+            inner: storage::JsonStorage::new(&directory, storage::StorageOptions::default()),
+            saves: saves.clone(),
+        };
+        let mut app = TodoApp::new(AppOptions { quiet: true, ..AppOptions::default() }, Box::new(storage));
+
+        app.begin_batch();
+        for number in 1..=100 {
+            assert_eq!(app.add_task(format!("Task {}", number), AddOptions::default()), Outcome::Success);
+        }
+        assert_eq!(saves.get(), 0);
+        assert_eq!(app.commit_batch(), Outcome::Success);
+        assert_eq!(saves.get(), 1);
+        assert_eq!(storage::parse_data(&std::fs::read_to_string(directory.join(".todo_data.json")).unwrap()).unwrap().1.len(), 100);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn cleanup_archives_only_tasks_done_before_the_cutoff() {
+        let now = Local::now().fixed_offset();
+        let completed_days_ago = |id: u32, description: &str, days: i64| {
+            let mut task = task_due(id, description, None);
+            task.complete(now - Duration::days(days));
+            task
+        };
+        let tasks = [
+            completed_days_ago(1, "Walk dog", 10),
+            completed_days_ago(2, "Buy milk", 5),
+            completed_days_ago(3, "Pay rent", 4),
+            task_due(4, "Call mom", None),
+        ];
+        let (directory, mut app) = app_with("cleanup", &tasks);
+
+        assert_eq!(app.cleanup(5, false), Outcome::Success);
+        let kept: Vec<u32> = app.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(kept, [2, 3, 4]);
+        let archived: Vec<u32> = app.storage.load_archive().iter().map(|task| task.id).collect();
+        assert_eq!(archived, [1]);
+
+        assert_eq!(app.cleanup(3, true), Outcome::Success);
+        assert_eq!(app.tasks.len(), 1);
+        assert_eq!(app.storage.load_archive().len(), 1);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn tag_variants_merge_into_one_lowercase_tag() {
+        let tag_list = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        let mut tasks = [task_due(1, "Write report", None), task_due(2, "Book flights", None), task_due(3, "Walk dog", None)];
+        tasks[0].tags = tag_list(&["Work", "work"]);
+        tasks[1].tags = tag_list(&["WORK", "travel"]);
+        tasks[2].tags = tag_list(&["pets"]);
+
+        let (changed, variants) = merge_tag_variants(&mut tasks);
+        assert_eq!(changed, 2);
+        assert_eq!(tasks[0].tags, ["work"]);
+        assert_eq!(tasks[1].tags, ["travel", "work"]);
+        assert_eq!(variants["work"].iter().collect::<Vec<_>>(), ["WORK", "Work"]);
+        assert_eq!(merge_tag_variants(&mut tasks).0, 0);
+    }
+
+    #[test]
+    fn missing_uids_are_filled_in_without_repeats() {
+        let mut tasks: Vec<Task> = (1..=200).map(|id| Task { uid: None, ..task_due(id, "Walk dog", None) }).collect();
+        assert!(assign_missing_uids(&mut tasks));
+        let uids: std::collections::HashSet<&str> = tasks.iter().map(|task| task.uid.as_deref().unwrap()).collect();
+        assert_eq!(uids.len(), tasks.len());
+        assert!(!assign_missing_uids(&mut tasks));
+    }
+}
@@ -1,17 +1,29 @@
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
 use clap::builder::Styles;
 use clap::builder::styling::AnsiColor;
 use clap_complete::{generate, Shell};
-use std::io;
-use crate::types::Priority;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use crate::export::{ExportField, ExportFormat};
+use crate::import::ImportFormat;
+use crate::storage;
+use crate::types::{self, AccentColor, ColorChoice, GroupBy, Priority, ReportPeriod, SortKey, TaskRef};
 
 
-const STYLES: Styles = Styles::styled()
-    .header(AnsiColor::Green.on_default().bold().underline())
-    .error(AnsiColor::Red.on_default().bold())
-    .usage(AnsiColor::Green.on_default().bold())
-    .literal(AnsiColor::Blue.on_default().bold())
-    .placeholder(AnsiColor::Cyan.on_default());
+const STYLES: Styles = styles(AnsiColor::Green);
+
+/// Help styles with section headers in `accent`, so each list's help can
+/// match its configured color
+pub const fn styles(accent: AnsiColor) -> Styles {
+    Styles::styled()
+        .header(accent.on_default().bold().underline())
+        .error(AnsiColor::Red.on_default().bold())
+        .usage(AnsiColor::Green.on_default().bold())
+        .literal(AnsiColor::Blue.on_default().bold())
+        .placeholder(AnsiColor::Cyan.on_default())
+}
 
 #[derive(Parser)]
 #[command(name = env!("CARGO_PKG_NAME"), author = "Jonathan Barrett <psveagle@pm.me>")]
@@ -19,6 +31,50 @@ const STYLES: Styles = Styles::styled()
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(styles = STYLES)]
 pub struct Cli {
+    /// Suppress informational messages; warnings and errors still go to stderr
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Verify the data file against its `.sha256` checksum and warn if it was modified
+    #[arg(long, global = true)]
+    pub verify_checksum: bool,
+
+    /// Store completion timestamps in UTC; existing local timestamps are converted on the next save
+    #[arg(long, global = true)]
+    pub utc: bool,
+
+    /// Don't lock the data file (concurrent runs may overwrite each other's changes)
+    #[arg(long, global = true)]
+    pub no_lock: bool,
+
+    /// When to use ANSI colors and emoji markers [default: auto]
+    #[arg(long, global = true, value_enum, value_name = "WHEN")]
+    pub color: Option<ColorChoice>,
+
+    /// Same as `--color never`
+    #[arg(long, global = true, hide = true, conflicts_with = "color")]
+    pub no_color: bool,
+
+    /// Show ASCII markers such as [!] instead of emoji, for terminals that can't display them
+    #[arg(long, global = true)]
+    pub plain_emoji: bool,
+
+    /// Show dates in this strftime format, e.g. "%d/%m/%Y" [default: %Y-%m-%d]
+    #[arg(long, global = true, value_name = "FORMAT", value_parser = types::parse_date_format)]
+    pub date_format: Option<String>,
+
+    /// Refuse due dates earlier than today when adding or editing tasks
+    #[arg(long, global = true)]
+    pub strict_dates: bool,
+
+    /// Don't print the overdue and due-today reminder before running the command
+    #[arg(long, global = true)]
+    pub no_reminders: bool,
+
+    /// Work on a separate, named task list instead of the default one
+    #[arg(short = 'L', long, global = true, value_parser = parse_list_name)]
+    pub list: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -28,83 +84,637 @@ pub enum Commands {
     /// Add a new task
     #[command(visible_aliases = ["+", "a"])]
     Add {
-        /// Task description
-        description: String,
+        /// Task description, prompted for interactively when omitted; `-` reads it from stdin
+        description: Option<String>,
 
-        /// Optional
-        #[arg(short,long, value_enum)]
+        /// Optional priority: high, medium or low (h/med/1-3 also work)
+        #[arg(short, long)]
         priority: Option<Priority>,
 
         /// Optional tags, comma-separated
         #[arg(short, long)]
         tags: Option<String>,
 
-        /// Optional due date in YYYY-MM-DD format
+        /// Optional due date: YYYY-MM-DD, YYYY-Www, today, tomorrow, +3d, +2w or a weekday
         #[arg(short, long)]
         due: Option<String>,
 
+        /// Optional time estimate, e.g. 45m, 2h or 1h30m
+        #[arg(short, long, value_parser = types::parse_duration)]
+        estimate: Option<u32>,
+
+        /// Optional color label, shown as a colored dot
+        #[arg(short, long, value_enum)]
+        label: Option<AccentColor>,
+
+        /// Add the task even if an active task already has the same description
+        #[arg(short, long)]
+        force: bool,
+
+        /// With `-`, add one task per line of stdin instead of one for all of it
+        #[arg(long)]
+        split_lines: bool,
     },
-    /// Edit an existing task by ID, you can change the description and/or due date
+    /// Add a task for each line of a file; `#tag` and `due:DATE` words set tags and due dates
+    AddMany {
+        /// Text file with one task per line
+        file: PathBuf,
+        /// Add tasks even if an active task already has the same description
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Edit an existing task by ID: description, priority, tags, due date, estimate or label
     Edit {
-        /// Task ID
-        id: u32,
+        /// Task ID, or the start of its uid
+        id: TaskRef,
         /// New task description
         description: Option<String>,
-        /// Task priority (high, medium, low)
-        #[arg(short, long, value_enum)]
-        priority: Option<crate::types::Priority>,
+        /// Edit the description in $EDITOR
+        #[arg(long, conflicts_with = "description")]
+        editor: bool,
+        /// Task priority: high, medium or low (h/med/1-3 also work)
+        #[arg(short, long)]
+        priority: Option<Priority>,
         /// Replace task tags (comma-separated)
-        #[arg(short = 't', long = "tags")]
-        tag: Option<String>,
+        #[arg(short, long)]
+        tags: Option<String>,
         /// Append tags to existing tags (comma-separated)
         #[arg(short = 'A', long = "add-tags")]
         add_tags: Option<String>,
-        /// Optional new due date in YYYY-MM-DD format
+        /// Remove individual tags (comma-separated, case-insensitive)
+        #[arg(short = 'R', long = "remove-tags")]
+        remove_tags: Option<String>,
+        /// Optional new due date: YYYY-MM-DD, YYYY-Www, today, tomorrow, +3d, +2w or a weekday
         #[arg(short, long)]
         due: Option<String>,
+        /// New time estimate, e.g. 45m, 2h or 1h30m
+        #[arg(short, long, value_parser = types::parse_duration)]
+        estimate: Option<u32>,
+        /// New color label
+        #[arg(short, long, value_enum)]
+        label: Option<AccentColor>,
+        /// Remove the task's priority
+        #[arg(long, conflicts_with = "priority")]
+        clear_priority: bool,
+        /// Remove all of the task's tags
+        #[arg(long, conflicts_with = "tags")]
+        clear_tags: bool,
+        /// Remove the task's due date
+        #[arg(long, conflicts_with = "due")]
+        clear_due: bool,
+        /// Remove the task's color label
+        #[arg(long, conflicts_with = "label")]
+        clear_label: bool,
+    },
+    /// Change every task matching the filters at once
+    #[command(group(ArgGroup::new("filters").required(true).multiple(true)))]
+    #[command(group(ArgGroup::new("changes").required(true).multiple(true)))]
+    BulkEdit {
+        /// Only tasks with this tag (case-insensitive)
+        #[arg(long, group = "filters")]
+        tag: Option<String>,
+        /// Only overdue tasks
+        #[arg(long, group = "filters")]
+        overdue: bool,
+        /// Only completed tasks
+        #[arg(long, group = "filters")]
+        completed: bool,
+        /// Set the priority: high, medium or low
+        #[arg(long, group = "changes", value_name = "LEVEL")]
+        set_priority: Option<Priority>,
+        /// Append tags (comma-separated)
+        #[arg(long, group = "changes", value_name = "TAGS")]
+        add_tags: Option<String>,
+        /// Push due dates back, e.g. 3d or 1w; overdue tasks move that far from today
+        #[arg(long, group = "changes", value_name = "DAYS", value_parser = types::parse_days)]
+        snooze: Option<i64>,
+    },
+    /// Save and reuse task templates for repetitive work
+    Template {
+        #[command(subcommand)]
+        action: TemplateCommands,
+    },
+    /// Show one task in full
+    Show {
+        /// Task ID, or the start of its uid
+        id: TaskRef,
     },
     /// List all tasks
     List {
-        /// Show only tasks due soon ( within 3 days)
+        /// Show only overdue tasks and tasks due soon (within 3 days unless --within is given)
         #[arg(short, long)]
         urgent: bool,
+        /// Number of days ahead that --urgent looks
+        #[arg(short, long, value_name = "DAYS")]
+        within: Option<i64>,
+        /// Let --urgent show completed tasks that were overdue or due soon too, e.g. for auditing
+        #[arg(long, requires = "urgent", conflicts_with = "active")]
+        include_completed: bool,
+        /// Hide completed tasks
+        #[arg(short, long)]
+        active: bool,
+        /// Print only the number of matching tasks
+        #[arg(short, long)]
+        count: bool,
+        /// Only show tasks estimated to fit in this many minutes
+        #[arg(long, value_name = "MIN")]
+        max_time: Option<u32>,
+        /// Order tasks by this key [default: due]
+        #[arg(short, long, value_enum)]
+        sort: Option<SortKey>,
+        /// Reverse the sorted order
+        #[arg(short, long)]
+        reverse: bool,
+        /// Show tasks under a header per tag or priority
+        #[arg(short, long, value_enum)]
+        group_by: Option<GroupBy>,
+        /// Custom line format, e.g. "{id}\t{desc} ({due})".
+        /// Placeholders: {id}, {uid}, {desc}, {due}, {priority}, {tags}, {status}
+        #[arg(short, long)]
+        format: Option<String>,
+        /// Show tasks as aligned columns, one row per task
+        #[arg(long, conflicts_with = "format")]
+        table: bool,
+        /// Show each task on a single line: [ ] 3: desc (due 2025-06-01) !HIGH #a,#b
+        #[arg(long, conflicts_with_all = ["format", "table", "pretty"])]
+        compact: bool,
+        /// Show each task as a block with its priority and tags on separate lines (the default)
+        #[arg(long)]
+        pretty: bool,
+        /// Show every due date as YYYY-MM-DD with how far off it is, e.g. "(in 2 days)"
+        #[arg(long, conflicts_with_all = ["format", "table"])]
+        absolute_dates: bool,
+        /// Print each task as one line of JSON, for tools like jq
+        #[arg(long, conflicts_with_all = ["format", "table", "compact", "group_by", "count"])]
+        json_lines: bool,
+        /// Print each task as a tab-separated line: id, status, description, due, priority, tags
+        #[arg(long, conflicts_with_all = ["format", "table", "compact", "group_by", "count", "json_lines"])]
+        tsv: bool,
+        /// Print only the ID of each matching task, one per line
+        #[arg(long, conflicts_with_all = ["format", "table", "compact", "group_by", "count", "json_lines", "tsv"])]
+        ids_only: bool,
+        /// Print descriptions (or IDs, with --ids-only) separated by NUL bytes, for xargs -0
+        #[arg(long, conflicts_with_all = ["format", "table", "compact", "group_by", "count", "json_lines", "tsv"])]
+        print0: bool,
+        /// Shorten descriptions to fit this many columns [default: terminal width]
+        #[arg(long, value_name = "N")]
+        width: Option<usize>,
+        /// Only show tasks completed on or after this date, e.g. 2025-06-01 or -7d
+        #[arg(long, value_name = "DATE", allow_hyphen_values = true)]
+        since: Option<String>,
+        /// Only show tasks completed on or before this date
+        #[arg(long, value_name = "DATE", allow_hyphen_values = true)]
+        until: Option<String>,
+        /// Only show tasks with this color label
+        #[arg(long, value_enum)]
+        label: Option<AccentColor>,
+    },
+    /// List tasks due on a specific date
+    Due {
+        /// The date: YYYY-MM-DD, YYYY-Www, today, tomorrow, +3d, +2w or a weekday
+        date: String,
     },
+    /// Show upcoming tasks grouped by day
+    Agenda {
+        /// Number of days to show, starting today
+        #[arg(short, long, default_value_t = 7)]
+        days: i64,
+    },
+    /// Show overdue, due-today, upcoming and undated tasks in one overview
+    All,
+    /// Send a desktop notification for tasks due today or overdue, e.g. from cron
+    Notify,
+    /// Show task counts and the remaining estimated time
+    Stats,
+    /// Count tasks per tag or per priority
+    CountBy {
+        /// What to group the counts by
+        #[arg(value_enum)]
+        dimension: GroupBy,
+        /// Leave out completed tasks
+        #[arg(short, long)]
+        active_only: bool,
+    },
+    /// Summarize tasks completed this week or month
+    Report {
+        /// The span to report on, ending today
+        #[arg(short, long, value_enum, default_value_t)]
+        period: ReportPeriod,
+        /// Also show how many days in a row you've completed a task
+        #[arg(short, long)]
+        streak: bool,
+    },
+    /// Show the single most important active task
+    Next,
     /// Search tasks by keyword
     Search {
         /// Keyword to search for in task descriptions
         keyword: String,
+        /// Also search tasks that have been archived
+        #[arg(long)]
+        include_archived: bool,
+        /// Treat the keyword as a regular expression, e.g. "^buy .*milk$"
+        #[arg(long)]
+        regex: bool,
+        /// Match upper and lower case exactly
+        #[arg(long)]
+        case_sensitive: bool,
+        /// Leave out completed tasks
+        #[arg(short, long)]
+        active: bool,
+        /// Order results by this key instead of the order they were added
+        #[arg(short, long, value_enum)]
+        sort: Option<SortKey>,
+        /// Print only the ID of each matching task, one per line
+        #[arg(long, conflicts_with = "include_archived")]
+        ids_only: bool,
+        /// Print descriptions (or IDs, with --ids-only) separated by NUL bytes, for xargs -0
+        #[arg(long, conflicts_with = "include_archived")]
+        print0: bool,
+    },
+    /// Find tasks with a query such as "tag:work AND priority:high OR due:<3d"
+    Find {
+        /// Conditions tag:NAME, priority:LEVEL, due:<Nd, due:>Nd or text:WORDS
+        /// (a bare word means text:), joined with AND / OR
+        query: String,
     },
     /// Mark a task as complete
     Complete {
-        /// Task ID
-        id: u32,
+        /// Task ID, or the start of its uid
+        #[arg(required_unless_present_any = ["all_overdue", "first", "last", "matching"])]
+        id: Option<TaskRef>,
+        /// Complete every overdue task instead of a single ID
+        #[arg(long, conflicts_with_all = ["id", "first", "last", "matching"])]
+        all_overdue: bool,
+        /// Complete the active task whose description contains this text,
+        /// choosing from a menu when several match
+        #[arg(short, long = "match", value_name = "TEXT", conflicts_with_all = ["id", "first", "last"])]
+        matching: Option<String>,
+        /// Complete the first N active tasks in list order
+        #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), conflicts_with_all = ["id", "last"])]
+        first: Option<usize>,
+        /// Complete the last N active tasks in list order
+        #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), conflicts_with = "id")]
+        last: Option<usize>,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Keep a task at the top of the list regardless of sort order
+    Pin {
+        /// Task ID, or the start of its uid
+        id: TaskRef,
+    },
+    /// Stop keeping a task at the top of the list
+    Unpin {
+        /// Task ID, or the start of its uid
+        id: TaskRef,
     },
     /// Mark multiple tasks as complete, provide a list of IDs
     CompleteTasks {
-        /// List of Task IDs to complete
-        ids: Vec<u32>,
+        /// Task IDs to complete; ranges like 3-7 are expanded
+        #[arg(required = true, value_parser = types::parse_id_range)]
+        ids: Vec<RangeInclusive<u32>>,
+    },
+    /// Move all completed tasks to the archive file
+    Archive,
+    /// Archive tasks completed more than a number of days ago
+    Cleanup {
+        /// Only tasks completed more than this many days ago
+        #[arg(long, value_name = "DAYS", value_parser = clap::value_parser!(i64).range(0..))]
+        older_than: i64,
+        /// Remove the tasks permanently instead of archiving them
+        #[arg(long)]
+        delete: bool,
     },
+    /// Lowercase all tags, merging variants like `Work` and `work`
+    NormalizeTags,
     /// Remove a task by ID
     #[command(visible_aliases = ["-", "rm", "del"])]
-    Remove { id: u32 },
+    Remove {
+        /// Task ID, or the start of its uid
+        #[arg(required_unless_present_any = ["first", "last"])]
+        id: Option<TaskRef>,
+        /// Remove the first N active tasks in list order
+        #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), conflicts_with_all = ["id", "last"])]
+        first: Option<usize>,
+        /// Remove the last N active tasks in list order
+        #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), conflicts_with = "id")]
+        last: Option<usize>,
+    },
+    /// Remove multiple tasks, provide a list of IDs
+    RemoveTasks {
+        /// Task IDs to remove; ranges like 3-7 are expanded
+        #[arg(required = true, value_parser = types::parse_id_range)]
+        ids: Vec<RangeInclusive<u32>>,
+    },
     /// Remove all tasks
     /// Use with caution!
     /// This will delete all tasks permanently.
-    RemoveAll,
+    RemoveAll {
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Export tasks to stdout in another format
+    Export {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// Write one JSON object per line instead, streamed for large lists
+        #[arg(long, conflicts_with = "format")]
+        json_lines: bool,
+        /// Write to this file instead of stdout, creating parent directories as needed
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Columns for CSV, in order, e.g. id,description,due
+        #[arg(long, value_enum, value_delimiter = ',', num_args = 1.., conflicts_with = "json_lines")]
+        fields: Vec<ExportField>,
+    },
+    /// Import tasks from a file, appending them to the list or merging by ID
+    Import {
+        /// File to import
+        file: String,
+        /// Input format
+        #[arg(short, long, value_enum, default_value_t = ImportFormat::Json)]
+        format: ImportFormat,
+        /// Leave out tasks whose description and due date match a task already in the list
+        #[arg(long)]
+        skip_duplicates: bool,
+        /// Match tasks by ID: update existing tasks in place and append the rest with their own IDs (JSON only)
+        #[arg(long, conflicts_with = "skip_duplicates")]
+        merge: bool,
+    },
+    /// Commit the data directory to git, then pull (rebasing) and push
+    Sync,
     /// Generate shell completions
     #[command(visible_aliases = ["comp"])]
     Completions {
         /// Shell to generate completions for
         #[arg(value_enum)]
         shell: Shell,
+        /// Write the script to the shell's completion directory instead of stdout
+        #[arg(long)]
+        install: bool,
+    },
+    /// Open the interactive terminal UI
+    Ui {
+        /// Seconds between checks for changes made by other `todo` processes
+        #[arg(short, long, default_value_t = 2)]
+        interval: u64,
+    },
+    /// Print active task IDs and descriptions for shell completion scripts
+    #[command(name = "complete-ids", hide = true)]
+    CompleteIds,
+    /// Print the configuration after applying the config file and flags
+    Config,
+    /// Print the name and version, optionally as JSON for other tools
+    Version {
+        /// Print `{"name": ..., "version": ...}` instead of plain text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Upgrade the data file to the current format, keeping a backup
+    Migrate,
+    /// Diagnose problems with the data file
+    Doctor {
+        /// Give tasks with duplicate IDs fresh, unique IDs
+        #[arg(long)]
+        repair: bool,
     },
-    Ui,
+}
+
+impl Commands {
+    /// Whether the command saves tasks, and so needs a writable data directory
+    pub fn modifies_tasks(&self) -> bool {
+        matches!(self,
+            Commands::Add { .. }
+            | Commands::AddMany { .. }
+            | Commands::Edit { .. }
+            | Commands::BulkEdit { .. }
+            | Commands::Template { action: TemplateCommands::Add { .. } }
+            | Commands::Complete { .. }
+            | Commands::Pin { .. }
+            | Commands::Unpin { .. }
+            | Commands::CompleteTasks { .. }
+            | Commands::Archive
+            | Commands::Cleanup { .. }
+            | Commands::NormalizeTags
+            | Commands::Remove { .. }
+            | Commands::RemoveTasks { .. }
+            | Commands::RemoveAll { .. }
+            | Commands::Import { .. }
+            | Commands::Sync
+            | Commands::Migrate
+            | Commands::Doctor { repair: true })
+    }
 }
 
 impl Cli {
     pub fn generate_completions(shell: Shell) {
+        Self::write_completions(shell, &mut io::stdout());
+    }
+
+    /// Writes the static completion script, plus dynamic task ID completion
+    /// for the shells that can call back into `todo`
+    fn write_completions(shell: Shell, out: &mut dyn Write) {
         let mut cmd = Self::command();
         let bin_name = cmd.get_name().to_string();
-        generate(shell, &mut cmd, bin_name, &mut io::stdout());
+        let id_commands = Self::id_subcommands(&cmd);
+        let mut script = Vec::new();
+        generate(shell, &mut cmd, bin_name.clone(), &mut script);
+        let mut script = String::from_utf8_lossy(&script).into_owned();
+
+        // `complete-ids` prints "id<TAB>description" for each active task
+        match shell {
+            Shell::Bash => {
+                script.push_str(&format!(
+                    r#"
+_{0}_with_ids() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "${{COMP_WORDS[1]}}" in
+        {1})
+            if [[ "$cur" != -* && ( "$prev" == "${{COMP_WORDS[1]}}" || "$prev" =~ ^[0-9]+$ ) ]]; then
+                COMPREPLY=($(compgen -W "$({0} complete-ids 2>/dev/null | cut -f1)" -- "$cur"))
+                return 0
+            fi
+            ;;
+    esac
+    _{0} "$@"
+}}
+complete -F _{0}_with_ids -o bashdefault -o default {0}
+"#,
+                    bin_name,
+                    id_commands.join("|")
+                ));
+            }
+            Shell::Zsh => {
+                // Point each task ID argument at a helper, defined before the
+                // trailing block that runs or registers `_todo`
+                let helper = format!(
+                    r#"(( $+functions[_{0}_ids] )) ||
+_{0}_ids() {{
+    local -a ids
+    ids=(${{${{(f)"$({0} complete-ids 2>/dev/null)"}}/$'\t'/:}})
+    _describe -t ids 'task ID' ids
+}}
+
+"#,
+                    bin_name
+                );
+                script = script
+                    .lines()
+                    .map(|line| {
+                        if line.starts_with("'::id -- ") || line.starts_with("'*::ids -- ") {
+                            line.replace(":_default'", &format!(":_{}_ids'", bin_name))
+                        } else {
+                            line.to_string()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    + "\n";
+                let trailer = format!("if [ \"$funcstack[1]\" = \"_{}\" ]", bin_name);
+                match script.find(&trailer) {
+                    Some(at) => script.insert_str(at, &helper),
+                    None => script.push_str(&helper),
+                }
+            }
+            Shell::Fish => {
+                // fish shows the text after the tab as the candidate's description
+                script.push_str(&format!(
+                    "complete -c {0} -n \"__fish_seen_subcommand_from {1}\" -f -a \"({0} complete-ids)\"\n",
+                    bin_name,
+                    id_commands.join(" ")
+                ));
+            }
+            _ => {}
+        }
+        let _ = out.write_all(script.as_bytes());
     }
-}
\ No newline at end of file
+
+    /// Names and aliases of the subcommands whose positional argument is a task ID
+    fn id_subcommands(cmd: &clap::Command) -> Vec<String> {
+        cmd.get_subcommands()
+            .filter(|sub| sub.get_positionals().any(|arg| matches!(arg.get_id().as_str(), "id" | "ids")))
+            .flat_map(|sub| std::iter::once(sub.get_name()).chain(sub.get_all_aliases()))
+            .filter(|name| name.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Where each shell looks for user completion scripts, if it has a
+    /// conventional per-user location
+    fn completion_install_path(shell: Shell, bin_name: &str) -> Option<PathBuf> {
+        match shell {
+            // Loaded on demand by bash-completion 2.x
+            Shell::Bash => dirs::data_dir()
+                .map(|dir| dir.join("bash-completion").join("completions").join(bin_name)),
+            Shell::Fish => dirs::config_dir()
+                .map(|dir| dir.join("fish").join("completions").join(format!("{}.fish", bin_name))),
+            // Needs `fpath+=~/.zfunc` before `compinit` in ~/.zshrc
+            Shell::Zsh => dirs::home_dir()
+                .map(|dir| dir.join(".zfunc").join(format!("_{}", bin_name))),
+            _ => None,
+        }
+    }
+
+    /// Writes the completion script for `shell` to its conventional location
+    ///
+    /// # Returns
+    /// * `io::Result<Option<PathBuf>>` - The path written, or `None` if the shell
+    ///   has no known location (the caller should fall back to stdout)
+    pub fn install_completions(shell: Shell) -> io::Result<Option<PathBuf>> {
+        let bin_name = Self::command().get_name().to_string();
+        let Some(path) = Self::completion_install_path(shell, &bin_name) else {
+            return Ok(None);
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::File::create(&path)?;
+        Self::write_completions(shell, &mut file);
+        Ok(Some(path))
+    }
+}
+
+/// Subcommands of `todo template`
+#[derive(Subcommand)]
+pub enum TemplateCommands {
+    /// Save a template, replacing any existing one with the same name
+    Save {
+        /// Template name (letters, digits, '-' and '_')
+        #[arg(value_parser = parse_list_name)]
+        name: String,
+        /// Description for tasks made from the template
+        description: String,
+        /// Priority: high, medium or low (h/med/1-3 also work)
+        #[arg(short, long)]
+        priority: Option<Priority>,
+        /// Tags, comma-separated
+        #[arg(short, long)]
+        tags: Option<String>,
+        /// Time estimate, e.g. 45m, 2h or 1h30m
+        #[arg(short, long, value_parser = types::parse_duration)]
+        estimate: Option<u32>,
+    },
+    /// Add a task from a template
+    Add {
+        /// Name of the saved template to use (see `todo template list`)
+        #[arg(value_parser = parse_list_name)]
+        name: String,
+        /// Due date: YYYY-MM-DD, YYYY-Www, today, tomorrow, +3d, +2w or a weekday
+        #[arg(short, long)]
+        due: Option<String>,
+        /// Add the task even if an active task already has the same description
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// List saved templates
+    List,
+}
+
+/// Rejects list names that would escape the data directory
+fn parse_list_name(name: &str) -> Result<String, String> {
+    if storage::is_valid_list_name(name) {
+        Ok(name.to_string())
+    } else {
+        Err("names may only contain letters, digits, '-' and '_'".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn template_add_documents_its_name() {
+        let mut command = Cli::command();
+        let add = command.find_subcommand_mut("template").unwrap().find_subcommand_mut("add").unwrap();
+        let name = add.get_arguments().find(|arg| arg.get_id() == "name").unwrap();
+        assert!(name.get_help().is_some());
+    }
+
+    #[test]
+    fn parse_list_name_rejects_paths() {
+        assert_eq!(parse_list_name("work_2025"), Ok("work_2025".to_string()));
+        assert!(parse_list_name("../etc").is_err());
+        assert!(parse_list_name("a/b").is_err());
+    }
+
+    #[test]
+    fn completions_hook_up_task_ids() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let mut script = Vec::new();
+            Cli::write_completions(shell, &mut script);
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.contains("todo complete-ids"), "{} script has no ID hook", shell);
+        }
+    }
+}
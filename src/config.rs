@@ -0,0 +1,104 @@
+use crate::storage::BackendKind;
+use crate::types::{AccentColor, LimitAction, Priority, SortKey};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Days ahead `list --urgent` looks when neither `--within` nor the config sets it
+pub const DEFAULT_URGENCY_DAYS: i64 = 3;
+
+/// Key in `list_colors` for the list used without `--list`
+const DEFAULT_LIST_NAME: &str = "default";
+
+/// User defaults read from `config.toml`. Every key is optional, and a flag
+/// given on the command line always wins over the value here.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// How many days ahead `list --urgent` looks for due tasks
+    pub urgency_days: Option<i64>,
+    /// Order used by `list` when `--sort` is omitted
+    pub sort: Option<SortKey>,
+    /// Task list used when `--list` is omitted
+    pub list: Option<String>,
+    /// `false` behaves like `--color never`; `true` or unset uses colors only
+    /// on a terminal, so only `--color always` forces them
+    pub color: Option<bool>,
+    /// Set to `true` to behave as if `--plain-emoji` were always given
+    pub plain_emoji: Option<bool>,
+    /// Set to `true` to behave as if `--strict-dates` were always given
+    pub strict_dates: Option<bool>,
+    /// Set to `false` to behave as if `--no-reminders` were always given
+    pub reminders: Option<bool>,
+    /// Set to `true` to merge case variants of tags every time tasks are loaded
+    pub normalize_tags: Option<bool>,
+    /// Priority for tasks added without `--priority`; unset, they get none
+    #[serde(deserialize_with = "deserialize_priority")]
+    pub default_priority: Option<Priority>,
+    /// strftime format for showing dates, as with `--date-format`
+    pub date_format: Option<String>,
+    /// Where tasks are stored: `json` (the default) or `sqlite`
+    pub backend: Option<BackendKind>,
+    /// Append single-task changes to a journal instead of rewriting the data file
+    pub journal: Option<bool>,
+    /// Set to `true` to show completed tasks dimmed and struck through, in
+    /// colored output and the TUI
+    pub strike_completed: Option<bool>,
+    /// Most tasks a list may hold before `add` stops; unset, there's no limit
+    pub max_tasks: Option<usize>,
+    /// Whether `add` past `max_tasks` is refused (the default) or only warned about
+    pub max_tasks_action: Option<LimitAction>,
+    /// Accent color for each list's titles and help headers; the list used
+    /// without `--list` is named `default`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub list_colors: BTreeMap<String, AccentColor>,
+}
+
+impl Config {
+    /// Location of the config file, e.g. `~/.config/todo-cli/config.toml` on Linux
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("todo-cli").join("config.toml"))
+    }
+
+    /// Reads the config file. A missing file gives the defaults; an unreadable
+    /// or invalid one warns and also falls back to the defaults.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                eprintln!("Warning: Could not read {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Warning: Ignoring invalid config file {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Accent color configured for a list, `None` meaning the default list
+    pub fn list_color(&self, list: Option<&str>) -> Option<AccentColor> {
+        self.list_colors.get(list.unwrap_or(DEFAULT_LIST_NAME)).copied()
+    }
+
+    /// Renders the config as TOML, for `todo config`
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+}
+
+/// Reads a priority the way `--priority` does, so the config accepts `medium`,
+/// `med` or `2` as well as the stored form `Medium`
+fn deserialize_priority<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Priority>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|priority| priority.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
@@ -0,0 +1,291 @@
+use crate::task::Task;
+use crate::todotxt;
+use chrono::Utc;
+use clap::ValueEnum;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Formats supported by `todo export`
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// A JSON array of tasks
+    Json,
+    /// iCalendar, one all-day event per task with a due date
+    Ics,
+    /// One todo.txt task per line
+    TodoTxt,
+    /// Comma-separated values with a header row; pick columns with `--fields`
+    Csv,
+}
+
+/// Columns `export --format csv --fields` can select
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportField {
+    Id,
+    Uid,
+    Description,
+    Priority,
+    Tags,
+    Completed,
+    Due,
+    Estimate,
+    Label,
+    Pinned,
+    CompletedAt,
+}
+
+impl ExportField {
+    /// Column header, as written on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportField::Id => "id",
+            ExportField::Uid => "uid",
+            ExportField::Description => "description",
+            ExportField::Priority => "priority",
+            ExportField::Tags => "tags",
+            ExportField::Completed => "completed",
+            ExportField::Due => "due",
+            ExportField::Estimate => "estimate",
+            ExportField::Label => "label",
+            ExportField::Pinned => "pinned",
+            ExportField::CompletedAt => "completed-at",
+        }
+    }
+
+    /// This column's cell for `task`, unquoted; empty when the task has no value
+    fn value(self, task: &Task) -> String {
+        match self {
+            ExportField::Id => task.id.to_string(),
+            ExportField::Uid => task.uid.clone().unwrap_or_default(),
+            ExportField::Description => task.description.clone(),
+            ExportField::Priority => task.priority.as_ref()
+                .map(|priority| format!("{:?}", priority).to_lowercase())
+                .unwrap_or_default(),
+            // Tags can't contain spaces, so a space keeps the cell easy to split
+            ExportField::Tags => task.tags.join(" "),
+            ExportField::Completed => task.completed.to_string(),
+            ExportField::Due => task.due_date.map(|due| due.to_string()).unwrap_or_default(),
+            ExportField::Estimate => task.estimate_minutes.map(|minutes| minutes.to_string()).unwrap_or_default(),
+            ExportField::Label => task.label.map(|label| label.name().to_string()).unwrap_or_default(),
+            ExportField::Pinned => task.pinned.to_string(),
+            ExportField::CompletedAt => task.completed_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Columns written when `--fields` isn't given
+pub const DEFAULT_CSV_FIELDS: [ExportField; 7] = [
+    ExportField::Id,
+    ExportField::Description,
+    ExportField::Priority,
+    ExportField::Tags,
+    ExportField::Due,
+    ExportField::Completed,
+    ExportField::CompletedAt,
+];
+
+pub fn to_json(tasks: &[Task]) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(tasks)
+}
+
+/// Creates (or truncates) the file `export --output` writes to, creating any
+/// missing parent directories first
+pub fn create_output(path: &Path) -> io::Result<File> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    File::create(path)
+}
+
+/// Writes each task as a JSON object on its own line (NDJSON), streaming them
+/// to `writer` one at a time rather than building the whole document first
+pub fn write_json_lines<'a>(tasks: impl IntoIterator<Item = &'a Task>, mut writer: impl Write) -> io::Result<()> {
+    for task in tasks {
+        serde_json::to_writer(&mut writer, task)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Renders tasks as CSV (RFC 4180): a header row naming `fields`, then one
+/// row per task with exactly those columns, in that order
+pub fn to_csv(tasks: &[Task], fields: &[ExportField]) -> String {
+    let row = |cells: Vec<String>| cells.iter()
+        .map(|cell| escape_csv_field(cell))
+        .collect::<Vec<_>>()
+        .join(",") + "\r\n";
+
+    let mut csv = row(fields.iter().map(|field| field.name().to_string()).collect());
+    for task in tasks {
+        csv.push_str(&row(fields.iter().map(|field| field.value(task)).collect()));
+    }
+    csv
+}
+
+/// Quotes a field when it holds a comma, quote or line break, doubling any quotes
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes each task as a tab-separated line: ID, status (`done` or `todo`),
+/// description, due date, priority and comma-joined tags, with empty fields
+/// for missing values
+pub fn write_tsv<'a>(tasks: impl IntoIterator<Item = &'a Task>, mut writer: impl Write) -> io::Result<()> {
+    for task in tasks {
+        let fields = [
+            ExportField::Id.value(task),
+            if task.completed { "done" } else { "todo" }.to_string(),
+            // A tab or line break in the description would shift the columns
+            task.description.replace(['\t', '\n', '\r'], " "),
+            ExportField::Due.value(task),
+            ExportField::Priority.value(task),
+            task.tags.join(","),
+        ];
+        writeln!(writer, "{}", fields.join("\t"))?;
+    }
+    writer.flush()
+}
+
+pub fn to_todo_txt(tasks: &[Task]) -> String {
+    tasks.iter()
+        .map(|task| todotxt::format_task(task) + "\n")
+        .collect()
+}
+
+/// Renders tasks with a due date as an iCalendar (RFC 5545) document.
+/// Tasks without a due date have nothing to place on a calendar and are skipped.
+pub fn to_ics(tasks: &[Task]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//{}//{}//EN", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+    ];
+
+    for task in tasks {
+        let Some(due) = task.due_date else { continue };
+        // VEVENT has no "completed" status; calendars render CANCELLED as struck through,
+        // which is the closest match for a finished task
+        let status = if task.completed { "CANCELLED" } else { "CONFIRMED" };
+
+        lines.push("BEGIN:VEVENT".to_string());
+        // IDs are reused after a task is removed, which would make calendars
+        // merge unrelated events; the uid never is
+        let key = task.uid.clone().unwrap_or_else(|| task.id.to_string());
+        lines.push(format!("UID:task-{}@{}", key, env!("CARGO_PKG_NAME")));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", due.format("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape_ics_text(&task.description)));
+        lines.push(format!("STATUS:{}", status));
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    // RFC 5545 requires CRLF line endings
+    lines.iter().map(|line| fold_ics_line(line)).collect::<Vec<_>>().join("\r\n") + "\r\n"
+}
+
+/// Most octets an iCalendar line may hold before it has to be folded
+const ICS_LINE_LIMIT: usize = 75;
+
+/// Folds a content line longer than 75 octets onto continuation lines, which
+/// start with a space (RFC 5545 section 3.1). Lines are only split between
+/// characters, so multi-byte UTF-8 stays intact.
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the next line's length
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+fn escape_ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Priority;
+    use chrono::NaiveDate;
+
+    fn task(id: u32, description: &str, due: Option<NaiveDate>) -> Task {
+        Task::new(id, description.to_string(), None, Vec::new(), due)
+    }
+
+    #[test]
+    fn ics_has_one_event_per_dated_task() {
+        let tasks = [
+            task(1, "Walk dog", NaiveDate::from_ymd_opt(2025, 6, 11)),
+            task(2, "Someday", None),
+            task(3, "Pay rent", NaiveDate::from_ymd_opt(2025, 7, 1)),
+        ];
+        let ics = to_ics(&tasks);
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains("DTSTART;VALUE=DATE:20250611\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20250701\r\n"));
+    }
+
+    #[test]
+    fn ics_uid_comes_from_the_task_uid() {
+        let mut dated = task(1, "Walk dog", NaiveDate::from_ymd_opt(2025, 6, 11));
+        dated.uid = Some("k3xq".to_string());
+        assert!(to_ics(std::slice::from_ref(&dated)).contains("UID:task-k3xq@todo\r\n"));
+        dated.uid = None;
+        assert!(to_ics(&[dated]).contains("UID:task-1@todo\r\n"));
+    }
+
+    #[test]
+    fn long_ics_lines_are_folded_at_75_octets() {
+        let description = "Plan the café opening ".repeat(10);
+        let ics = to_ics(&[task(1, &description, NaiveDate::from_ymd_opt(2025, 6, 11))]);
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+
+        let summary = ics.split("\r\nSUMMARY:").nth(1).unwrap();
+        let unfolded = summary.split("\r\nSTATUS:").next().unwrap().replace("\r\n ", "");
+        assert_eq!(unfolded, description);
+    }
+
+    #[test]
+    fn short_ics_lines_are_left_alone() {
+        assert_eq!(fold_ics_line("SUMMARY:Walk dog"), "SUMMARY:Walk dog");
+        let exact = "x".repeat(75);
+        assert_eq!(fold_ics_line(&exact), exact);
+        assert_eq!(fold_ics_line(&"x".repeat(76)), format!("{}\r\n x", exact));
+    }
+
+    #[test]
+    fn tsv_lines_split_into_six_fields() {
+        let mut tagged = task(1, "Walk\tthe dog", NaiveDate::from_ymd_opt(2025, 6, 11));
+        tagged.priority = Some(Priority::High);
+        tagged.tags = vec!["daily".to_string(), "pets".to_string()];
+        let mut done = task(2, "Buy milk", None);
+        done.completed = true;
+
+        let mut output = Vec::new();
+        write_tsv([&tagged, &done], &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<Vec<&str>> = output.lines().map(|line| line.split('\t').collect()).collect();
+        assert_eq!(lines, [
+            vec!["1", "todo", "Walk the dog", "2025-06-11", "high", "daily,pets"],
+            vec!["2", "done", "Buy milk", "", "", ""],
+        ]);
+    }
+}
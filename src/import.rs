@@ -0,0 +1,27 @@
+use crate::storage;
+use crate::task::Task;
+use crate::todotxt;
+use clap::ValueEnum;
+
+/// Formats supported by `todo import`
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ImportFormat {
+    /// A JSON array of tasks, or a copy of the data file
+    Json,
+    /// One todo.txt task per line
+    TodoTxt,
+}
+
+/// Parses an import file into tasks. IDs in the result are placeholders;
+/// the caller assigns real IDs when appending them to the list.
+pub fn parse(contents: &str, format: ImportFormat) -> Result<Vec<Task>, String> {
+    match format {
+        ImportFormat::Json => storage::parse_data(contents)
+            .map(|(_, tasks)| tasks)
+            .map_err(|e| e.to_string()),
+        ImportFormat::TodoTxt => Ok(contents
+            .lines()
+            .filter_map(|line| todotxt::parse_line(line, 0))
+            .collect()),
+    }
+}
@@ -1,52 +1,488 @@
 mod app;
 mod cli;
+mod config;
+mod export;
+mod import;
+mod notify;
+mod prompt;
+mod query;
+mod render;
 mod storage;
+mod sync;
 mod task;
+mod template;
+mod todotxt;
 mod types;
 mod ui;
 
-use clap::Parser;
-use cli::{Cli, Commands};
-use app::TodoApp;
+use clap::{CommandFactory, FromArgMatches};
+use clap::builder::styling::AnsiColor;
+use cli::{Cli, Commands, TemplateCommands};
+use config::{Config, DEFAULT_URGENCY_DAYS};
+use storage::StorageOptions;
+use app::{AddOptions, AppOptions, BulkEditOptions, EditOptions, ListOptions, SearchOptions, TodoApp};
+use std::time::Duration;
+use types::{ColorChoice, Outcome, TaskRef};
 
 fn main() {
-    let cli = Cli::parse();
-    let mut app = TodoApp::new();
+    // The config is read first so `--help` can use the default list's color
+    let config = Config::load();
+    let help_accent = config.list_color(config.list.as_deref()).map(Into::into).unwrap_or(AnsiColor::Green);
+    let matches = Cli::command().styles(cli::styles(help_accent)).get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    match cli.command {
-        Commands::Add { description, priority, tags, due } => {
-            app.add_task(description, priority, tags, due);
+    // Flags win over the config file, which wins over the built-in defaults
+    let color_choice = cli.color
+        .or(cli.no_color.then_some(ColorChoice::Never))
+        .or(config.color.map(|on| if on { ColorChoice::Auto } else { ColorChoice::Never }))
+        .unwrap_or_default();
+    let color = color_choice.enabled();
+    // Plain text output has no use for emoji either
+    let plain_emoji = cli.plain_emoji || config.plain_emoji.unwrap_or(false) || !color;
+    let strict_dates = cli.strict_dates || config.strict_dates.unwrap_or(false);
+    let normalize_tags = config.normalize_tags.unwrap_or(false);
+    let reminders = !cli.no_reminders && config.reminders.unwrap_or(true);
+    let list = match cli.list.clone().or(config.list.clone()) {
+        Some(name) if !storage::is_valid_list_name(&name) => {
+            eprintln!("Error: invalid list name '{}' in config file", name);
+            std::process::exit(Outcome::BadInput.exit_code());
         }
-        Commands::Edit { id, description, priority, tag, add_tags, due } => {
-            app.edit_task(id, description, priority, tag, add_tags, due);
+        list => list,
+    };
+    let date_format = match cli.date_format.clone().or(config.date_format.clone()) {
+        Some(format) if types::parse_date_format(&format).is_err() => {
+            eprintln!("Error: invalid date_format '{}' in config file", format);
+            std::process::exit(Outcome::BadInput.exit_code());
         }
-        Commands::List { urgent } => {
-            app.list_tasks(urgent);
+        date_format => date_format,
+    };
+    let backend = config.backend.unwrap_or_default();
+
+    // Needs no task data, so it works even when the data directory doesn't
+    if let Commands::Version { json } = cli.command {
+        if json {
+            println!("{}", serde_json::json!({
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            }));
+        } else {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        }
+        std::process::exit(Outcome::Success.exit_code());
+    }
+
+    let use_lock = !cli.no_lock;
+    let journal = config.journal.unwrap_or(false);
+    let storage_options = StorageOptions { verify_checksum: cli.verify_checksum, use_lock, journal };
+    let storage = match storage::open(backend, storage_options, list.as_deref()) {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(Outcome::IoError.exit_code());
+        }
+    };
+
+    // Refuse before changing anything rather than report success for changes
+    // that can't be saved
+    if cli.command.modifies_tasks()
+        && let Err(e) = storage.check_writable()
+    {
+        eprintln!("Error: can't save changes to {}: {}", storage.path().display(), e);
+        eprintln!("Check that the data directory is writable; nothing was changed.");
+        std::process::exit(Outcome::IoError.exit_code());
+    }
+
+    let strike_completed = config.strike_completed.unwrap_or(false);
+    if let Commands::Ui { interval } = cli.command {
+        let outcome = match ui::run_ui(storage.as_ref(), Duration::from_secs(interval.max(1)), cli.utc, strike_completed) {
+            Ok(()) => Outcome::Success,
+            Err(e) => {
+                eprintln!("Error running UI: {}", e);
+                Outcome::IoError
+            }
+        };
+        std::process::exit(outcome.exit_code());
+    }
+
+    // Where the data file was just created, on the first run
+    let created_path = storage.first_run().then(|| storage.path().to_path_buf());
+    let mut app = TodoApp::new(AppOptions {
+        quiet: cli.quiet,
+        utc: cli.utc,
+        color,
+        accent: config.list_color(list.as_deref()),
+        plain_emoji,
+        strict_dates,
+        normalize_tags,
+        date_format: date_format.clone(),
+        default_priority: config.default_priority.clone(),
+        max_tasks: config.max_tasks,
+        max_tasks_action: config.max_tasks_action.unwrap_or_default(),
+        strike_completed,
+        // `migrate` saves them itself, after reporting what it upgraded
+        save_on_load: cli.command.modifies_tasks() && !matches!(cli.command, Commands::Migrate),
+        // Read-only commands never wait for another process to finish saving
+        lock: cli.command.modifies_tasks(),
+    }, storage);
+
+    // The reminder goes to stderr so it never ends up in piped output, and is
+    // skipped for commands whose output is read by other programs or that run
+    // unattended
+    let skip_reminder = matches!(cli.command, Commands::Completions { .. } | Commands::CompleteIds | Commands::Config | Commands::Notify);
+    if let Some(path) = created_path
+        && !cli.quiet && !skip_reminder
+        && !matches!(cli.command, Commands::Add { .. } | Commands::AddMany { .. })
+    {
+        eprintln!("Welcome to todo! Your tasks will be kept in {}.", path.display());
+        eprintln!("Add your first one with: todo add \"Something to do\"");
+    }
+    if reminders && !cli.quiet && !skip_reminder
+        && let Some(banner) = app.reminder_banner()
+    {
+        eprintln!("{}", banner);
+    }
+
+    let outcome = match cli.command {
+        Commands::Add { description: Some(description), priority, tags, due, estimate, label, force, split_lines } => {
+            let options = AddOptions { priority, tags, due, estimate, label, force };
+            if description != "-" {
+                app.add_task(description, options)
+            } else {
+                match read_descriptions(split_lines) {
+                    Ok(descriptions) => add_each(&mut app, descriptions, options),
+                    Err(outcome) => outcome,
+                }
+            }
+        }
+        Commands::Add { description: None, force, .. } => {
+            if !prompt::is_interactive() {
+                eprintln!("Error: a task description is required");
+                Outcome::BadInput
+            } else {
+                let today = app.today();
+                match app.without_lock(|| prompt::add_wizard(today)) {
+                    Some(answers) => app.add_task(answers.description, AddOptions {
+                        priority: answers.priority,
+                        tags: answers.tags,
+                        due: answers.due,
+                        force,
+                        ..AddOptions::default()
+                    }),
+                    None => {
+                        eprintln!("No description given, task not added");
+                        Outcome::BadInput
+                    }
+                }
+            }
+        }
+        Commands::AddMany { file, force } => {
+            app.add_many(&file, force)
+        }
+        Commands::Edit {
+            id,
+            description,
+            editor,
+            priority,
+            tags,
+            add_tags,
+            remove_tags,
+            due,
+            estimate,
+            label,
+            clear_priority,
+            clear_tags,
+            clear_due,
+            clear_label,
+        } => {
+            with_task(&mut app, &id, |app, id| app.edit_task(id, EditOptions {
+                description,
+                editor,
+                priority,
+                tags,
+                add_tags,
+                remove_tags,
+                due,
+                estimate,
+                label,
+                clear_priority,
+                clear_tags,
+                clear_due,
+                clear_label,
+            }))
+        }
+        Commands::BulkEdit { tag, overdue, completed, set_priority, add_tags, snooze } => {
+            app.bulk_edit(BulkEditOptions { tag, overdue, completed, set_priority, add_tags, snooze })
+        }
+        Commands::Template { action: TemplateCommands::Save { name, description, priority, tags, estimate } } => {
+            app.save_template(&name, description, priority, tags, estimate)
+        }
+        Commands::Template { action: TemplateCommands::Add { name, due, force } } => {
+            app.add_from_template(&name, due, force)
+        }
+        Commands::Template { action: TemplateCommands::List } => {
+            app.list_templates()
+        }
+        Commands::Show { id } => {
+            with_task(&mut app, &id, |app, id| app.show_task(id))
+        }
+        Commands::List { urgent, within, include_completed, active, count, max_time, sort, reverse, group_by, format, table, compact, pretty: _, absolute_dates, json_lines, tsv, ids_only, print0, width, since, until, label } => {
+            app.list_tasks(&ListOptions {
+                urgent,
+                within: within.or(config.urgency_days).unwrap_or(DEFAULT_URGENCY_DAYS),
+                active,
+                count,
+                max_time,
+                sort: sort.or(config.sort).unwrap_or_default(),
+                reverse,
+                group_by,
+                format,
+                table,
+                compact,
+                absolute_dates,
+                json_lines,
+                tsv,
+                ids_only,
+                print0,
+                include_completed,
+                width: width.or_else(render::terminal_width),
+                since,
+                until,
+                label,
+            })
+        }
+        Commands::Due { date } => {
+            app.show_due_on(&date)
+        }
+        Commands::Agenda { days } => {
+            app.agenda(days)
+        }
+        Commands::All => {
+            app.overview()
+        }
+        Commands::Notify => {
+            app.notify()
+        }
+        Commands::Stats => {
+            app.stats()
+        }
+        Commands::CountBy { dimension, active_only } => {
+            app.count_by(dimension, active_only)
+        }
+        Commands::Report { period, streak } => {
+            app.report(period, streak)
+        }
+        Commands::Next => {
+            app.show_next()
+        }
+        Commands::Search { keyword, include_archived, regex, case_sensitive, active, sort, ids_only, print0 } => {
+            app.search_tasks(&keyword, &SearchOptions { include_archived, regex, case_sensitive, active, sort, ids_only, print0 })
+        }
+        Commands::Find { query } => {
+            app.find(&query)
         }
-        Commands::Search { keyword } => {
-            app.search_tasks(&keyword);
+        Commands::Complete { all_overdue: true, yes, .. } => {
+            let count = app.overdue_count();
+            if count > 0 && !yes && !app.without_lock(|| prompt::confirm(&format!("Mark {} overdue tasks as complete?", count))) {
+                eprintln!("Aborted, no tasks were completed");
+                Outcome::BadInput
+            } else {
+                app.complete_overdue()
+            }
+        }
+        Commands::Complete { matching: Some(text), .. } => {
+            app.complete_matching(&text)
+        }
+        Commands::Complete { first, last, .. } if first.is_some() || last.is_some() => {
+            let ids = app.select_ids(first, last, config.sort.unwrap_or_default());
+            apply_to_each(&mut app, ids, TodoApp::complete_task)
+        }
+        Commands::Complete { id: Some(id), .. } => {
+            with_task(&mut app, &id, TodoApp::complete_task)
+        }
+        Commands::Complete { id: None, .. } => unreachable!("clap requires an ID unless another selection is given"),
+        Commands::Pin { id } => {
+            with_task(&mut app, &id, |app, id| app.set_pinned(id, true))
         }
-        Commands::Complete { id } => {
-            app.complete_task(id);
+        Commands::Unpin { id } => {
+            with_task(&mut app, &id, |app, id| app.set_pinned(id, false))
         }
         Commands::CompleteTasks { ids } => {
-            for id in ids {
-                app.complete_task(id);
+            match types::expand_ids(ids, app.highest_id()) {
+                Ok(ids) => apply_to_each(&mut app, ids, TodoApp::complete_task),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    Outcome::BadInput
+                }
             }
         }
-        Commands::Remove { id } => {
-            app.remove_task(id);
+        Commands::RemoveTasks { ids } => {
+            match types::expand_ids(ids, app.highest_id()) {
+                Ok(ids) => apply_to_each(&mut app, ids, TodoApp::remove_task),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    Outcome::BadInput
+                }
+            }
+        }
+        Commands::Archive => {
+            app.archive_completed()
+        }
+        Commands::Cleanup { older_than, delete } => {
+            app.cleanup(older_than, delete)
+        }
+        Commands::NormalizeTags => {
+            app.normalize_tags()
+        }
+        Commands::Remove { first, last, .. } if first.is_some() || last.is_some() => {
+            let ids = app.select_ids(first, last, config.sort.unwrap_or_default());
+            apply_to_each(&mut app, ids, TodoApp::remove_task)
         }
-        Commands::RemoveAll => {
-            app.remove_all_tasks();
+        Commands::Remove { id: Some(id), .. } => {
+            with_task(&mut app, &id, TodoApp::remove_task)
         }
-        Commands::Completions { shell } => {
+        Commands::Remove { id: None, .. } => unreachable!("clap requires an ID unless --first or --last is given"),
+        Commands::RemoveAll { yes } => {
+            if !yes && !app.without_lock(|| prompt::confirm("Permanently delete ALL tasks?")) {
+                eprintln!("Aborted, no tasks were removed");
+                Outcome::BadInput
+            } else {
+                app.remove_all_tasks()
+            }
+        }
+        Commands::Export { format, json_lines, output, fields } => {
+            app.export(format, json_lines, output.as_deref(), &fields)
+        }
+        Commands::Import { file, format, merge: true, .. } => {
+            app.import_merge(&file, format)
+        }
+        Commands::Import { file, format, skip_duplicates, merge: false } => {
+            app.import(&file, format, skip_duplicates)
+        }
+        Commands::Sync => {
+            app.sync()
+        }
+        Commands::Completions { shell, install: true } => {
+            match Cli::install_completions(shell) {
+                Ok(Some(path)) => {
+                    println!("Installed {} completions to {}", shell, path.display());
+                    Outcome::Success
+                }
+                Ok(None) => {
+                    eprintln!("No standard completion directory for {}; printing to stdout instead", shell);
+                    Cli::generate_completions(shell);
+                    Outcome::Success
+                }
+                Err(e) => {
+                    eprintln!("Error: could not install completions: {}", e);
+                    Outcome::IoError
+                }
+            }
+        }
+        Commands::Completions { shell, install: false } => {
             Cli::generate_completions(shell);
+            Outcome::Success
         }
-        Commands::Ui => {
-            if let Err(e) = ui::run_ui() {
-                eprintln!("Error running UI: {}", e);
+        Commands::CompleteIds => {
+            app.print_completion_ids()
+        }
+        Commands::Config => {
+            let resolved = Config {
+                urgency_days: Some(config.urgency_days.unwrap_or(DEFAULT_URGENCY_DAYS)),
+                sort: Some(config.sort.unwrap_or_default()),
+                list,
+                color: Some(color),
+                plain_emoji: Some(plain_emoji),
+                strict_dates: Some(strict_dates),
+                reminders: Some(reminders),
+                normalize_tags: Some(normalize_tags),
+                default_priority: config.default_priority.clone(),
+                date_format: Some(date_format.unwrap_or_else(|| "%Y-%m-%d".to_string())),
+                backend: Some(backend),
+                journal: Some(journal),
+                strike_completed: Some(strike_completed),
+                max_tasks: config.max_tasks,
+                max_tasks_action: Some(config.max_tasks_action.unwrap_or_default()),
+                list_colors: config.list_colors.clone(),
+            };
+            if let Some(path) = Config::path() {
+                println!("# {}", path.display());
+            }
+            match resolved.to_toml() {
+                Ok(toml) => {
+                    print!("{}", toml);
+                    Outcome::Success
+                }
+                Err(e) => {
+                    eprintln!("Error: could not render config: {}", e);
+                    Outcome::IoError
+                }
             }
         }
+        Commands::Migrate => {
+            app.migrate()
+        }
+        Commands::Doctor { repair } => {
+            app.doctor(repair)
+        }
+        Commands::Ui { .. } | Commands::Version { .. } => unreachable!("handled before the app is created"),
+    };
+
+    std::process::exit(outcome.exit_code());
+}
+
+/// Runs a single-task command on the task `task` names, once it's resolved to an ID
+fn with_task(app: &mut TodoApp, task: &TaskRef, action: impl FnOnce(&mut TodoApp, u32) -> Outcome) -> Outcome {
+    match app.resolve(task) {
+        Ok(id) => action(app, id),
+        Err(outcome) => outcome,
+    }
+}
+
+/// Reads task descriptions piped to `add -`: all of stdin as one description,
+/// or one per non-blank line with `split_lines`
+fn read_descriptions(split_lines: bool) -> Result<Vec<String>, Outcome> {
+    let input = std::io::read_to_string(std::io::stdin()).map_err(|e| {
+        eprintln!("Error: could not read stdin: {}", e);
+        Outcome::IoError
+    })?;
+    let descriptions: Vec<String> = if split_lines {
+        input.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect()
+    } else {
+        Some(input.trim()).filter(|text| !text.is_empty()).map(String::from).into_iter().collect()
+    };
+    if descriptions.is_empty() {
+        eprintln!("Error: a task description is required, but stdin was empty");
+        return Err(Outcome::BadInput);
+    }
+    Ok(descriptions)
+}
+
+/// Adds a task for each description, saving once at the end. Keeps going
+/// past failures, but reports the first one in the exit code.
+fn add_each(app: &mut TodoApp, descriptions: Vec<String>, options: AddOptions) -> Outcome {
+    let mut outcome = Outcome::Success;
+    app.begin_batch();
+    for description in descriptions {
+        let result = app.add_task(description, options.clone());
+        if outcome.is_success() {
+            outcome = result;
+        }
+    }
+    let saved = app.commit_batch();
+    if outcome.is_success() { saved } else { outcome }
+}
+
+/// Runs a single-task command for each ID, saving once at the end. Keeps
+/// going past failures, but reports the first one in the exit code.
+fn apply_to_each(app: &mut TodoApp, ids: Vec<u32>, action: fn(&mut TodoApp, u32) -> Outcome) -> Outcome {
+    let mut outcome = Outcome::Success;
+    app.begin_batch();
+    for id in ids {
+        let result = action(app, id);
+        if outcome.is_success() {
+            outcome = result;
+        }
     }
+    let saved = app.commit_batch();
+    if outcome.is_success() { saved } else { outcome }
 }
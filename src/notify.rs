@@ -0,0 +1,124 @@
+// Desktop notifications for `todo notify`. Building the messages is always
+// available; sending them needs a build with `--features notify`.
+
+use crate::render;
+use crate::task::Task;
+use chrono::NaiveDate;
+
+/// Above this many due tasks, one summary replaces the per-task notifications
+pub const MAX_INDIVIDUAL_NOTIFICATIONS: usize = 3;
+
+/// Title and text of one desktop notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+}
+
+/// Builds the notifications for tasks that are overdue or due today
+///
+/// # Arguments
+/// * `tasks` - The due tasks, in the order they should be mentioned
+/// * `today` - The date due dates are compared against
+///
+/// # Returns
+/// * `Vec<Notification>` - One per task, or a single summary when there are
+///   more than `MAX_INDIVIDUAL_NOTIFICATIONS`
+pub fn build_notifications(tasks: &[&Task], today: NaiveDate) -> Vec<Notification> {
+    if tasks.len() <= MAX_INDIVIDUAL_NOTIFICATIONS {
+        return tasks.iter()
+            .map(|task| Notification {
+                summary: format!("Task #{}: {}", task.id, due_text(task, today)),
+                body: task.description.clone(),
+            })
+            .collect();
+    }
+
+    let overdue = tasks.iter().filter(|task| task.is_overdue(today, false)).count();
+    let due_today = tasks.len() - overdue;
+    let summary = match (overdue, due_today) {
+        (overdue, 0) => format!("{} tasks overdue", overdue),
+        (0, today) => format!("{} tasks due today", today),
+        (overdue, today) => format!("{} tasks overdue, {} due today", overdue, today),
+    };
+    let body = tasks.iter()
+        .map(|task| format!("#{} {} ({})", task.id, task.description, due_text(task, today)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    vec![Notification { summary, body }]
+}
+
+/// "due today" or e.g. "2 days overdue"
+fn due_text(task: &Task, today: NaiveDate) -> String {
+    match task.due_date {
+        Some(due) if due < today => render::relative_due(due, today),
+        _ => "due today".to_string(),
+    }
+}
+
+/// Shows a notification on the desktop
+#[cfg(feature = "notify")]
+pub fn send(notification: &Notification) -> Result<(), String> {
+    notify_rust::Notification::new()
+        .appname(env!("CARGO_PKG_NAME"))
+        .summary(&notification.summary)
+        .body(&notification.body)
+        .show()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "notify"))]
+pub fn send(_notification: &Notification) -> Result<(), String> {
+    Err("this build of todo doesn't include desktop notifications; rebuild with `--features notify`".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, day).unwrap()
+    }
+
+    fn task(id: u32, description: &str, due: NaiveDate) -> Task {
+        Task::new(id, description.to_string(), None, Vec::new(), Some(due))
+    }
+
+    #[test]
+    fn few_due_tasks_get_a_notification_each() {
+        let tasks = [task(1, "Walk dog", date(11)), task(2, "Pay rent", date(9))];
+        let notifications = build_notifications(&tasks.iter().collect::<Vec<_>>(), date(11));
+        assert_eq!(notifications, [
+            Notification { summary: "Task #1: due today".to_string(), body: "Walk dog".to_string() },
+            Notification { summary: "Task #2: 2 days overdue".to_string(), body: "Pay rent".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn many_due_tasks_are_summarized() {
+        let tasks = [
+            task(1, "Walk dog", date(11)),
+            task(2, "Pay rent", date(10)),
+            task(3, "Call mom", date(11)),
+            task(4, "File taxes", date(8)),
+        ];
+        let notifications = build_notifications(&tasks.iter().collect::<Vec<_>>(), date(11));
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].summary, "2 tasks overdue, 2 due today");
+        assert_eq!(notifications[0].body.lines().count(), 4);
+        assert!(notifications[0].body.contains("#4 File taxes (3 days overdue)"));
+    }
+
+    #[test]
+    fn summary_leaves_out_an_empty_half() {
+        let tasks: Vec<Task> = (1..=4).map(|id| task(id, "Walk dog", date(11))).collect();
+        let notifications = build_notifications(&tasks.iter().collect::<Vec<_>>(), date(11));
+        assert_eq!(notifications[0].summary, "4 tasks due today");
+    }
+
+    #[test]
+    fn nothing_due_means_no_notifications() {
+        assert!(build_notifications(&[], date(11)).is_empty());
+    }
+}
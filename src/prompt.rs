@@ -0,0 +1,314 @@
+use crate::task::{self, Task};
+use crate::types::{self, Priority};
+use chrono::NaiveDate;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Answers collected by the interactive `add` wizard, in the same shape
+/// `TodoApp::add_task` expects from the command line.
+pub struct AddAnswers {
+    pub description: String,
+    pub priority: Option<Priority>,
+    pub tags: Option<String>,
+    pub due: Option<String>,
+}
+
+/// Returns true when stdin is attached to a terminal, so prompting won't hang a pipe
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Prints `label` and reads one line from stdin
+///
+/// # Returns
+/// * `Option<String>` - The trimmed answer, or `None` on EOF/read error
+pub fn ask(label: &str) -> Option<String> {
+    ask_from(&mut io::stdin().lock(), &mut io::stdout(), label)
+}
+
+/// `ask` reading from `input` and prompting on `output`
+fn ask_from(input: &mut impl BufRead, output: &mut impl Write, label: &str) -> Option<String> {
+    write!(output, "{}", label).ok()?;
+    output.flush().ok()?;
+
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+/// Asks a yes/no question, defaulting to "no"
+///
+/// When stdin isn't a terminal there is nobody to answer, so this refuses
+/// rather than hanging or guessing; callers should offer a `--yes` flag.
+pub fn confirm(question: &str) -> bool {
+    if !is_interactive() {
+        eprintln!("Error: confirmation required but stdin is not a terminal; pass --yes to proceed");
+        return false;
+    }
+
+    confirm_from(&mut io::stdin().lock(), &mut io::stdout(), question)
+}
+
+/// `confirm` reading the answer from `input`, without the terminal check
+fn confirm_from(input: &mut impl BufRead, output: &mut impl Write, question: &str) -> bool {
+    matches!(
+        ask_from(input, output, &format!("{} [y/N] ", question)).map(|a| a.to_lowercase()).as_deref(),
+        Some("y") | Some("yes")
+    )
+}
+
+/// Shows a numbered menu of tasks and asks which ones to use
+///
+/// # Returns
+/// * `Option<Vec<u32>>` - IDs of the chosen tasks, or `None` if the user
+///   cancelled with `q` or closed stdin
+pub fn select_from(candidates: &[&Task]) -> Option<Vec<u32>> {
+    for (number, task) in candidates.iter().enumerate() {
+        println!("  {}) #{} {}", number + 1, task.id, task.description);
+    }
+    loop {
+        let answer = ask("Choose by number (e.g. 1,3 or 2-4), a for all, q to cancel: ")?;
+        if answer.eq_ignore_ascii_case("q") {
+            return None;
+        }
+        match parse_selection(&answer, candidates.len()) {
+            Ok(positions) => return Some(positions.into_iter().map(|i| candidates[i].id).collect()),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+/// Parses a menu answer into zero-based positions: `a` for all `count`
+/// entries, or one-based numbers and ranges separated by commas or spaces
+///
+/// # Returns
+/// * `Result<Vec<usize>, String>` - The positions in the order given, or why
+///   the answer doesn't make sense
+pub fn parse_selection(input: &str, count: usize) -> Result<Vec<usize>, String> {
+    if input.trim().eq_ignore_ascii_case("a") {
+        return Ok((0..count).collect());
+    }
+    let ranges = input.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| types::parse_id_range(part)
+            .map_err(|_| format!("'{}' is not a number or a range like 2-4", part)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let numbers = types::expand_ids(ranges, count as u32)
+        .map_err(|_| format!("choose numbers from 1 to {}", count))?;
+    if numbers.is_empty() {
+        return Err("choose at least one number".to_string());
+    }
+    numbers.into_iter()
+        .map(|number| match number as usize {
+            number if (1..=count).contains(&number) => Ok(number - 1),
+            number => Err(format!("{} is not in the menu, choose 1 to {}", number, count)),
+        })
+        .collect()
+}
+
+/// A file for `edit_in_editor`, removed again when dropped
+struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// Creates a new file under a random name in the temp directory, holding
+    /// `initial`. The name is never reused: in a shared `/tmp` a predictable
+    /// name could be planted as a symlink, so an existing file is never opened.
+    fn create(initial: &str) -> Result<Self, String> {
+        let mut attempts = 0;
+        loop {
+            // Three uids give 60 random bits, too many names to plant in advance
+            let name = format!("todo-edit-{}{}{}.txt", task::generate_uid(), task::generate_uid(), task::generate_uid());
+            let path = env::temp_dir().join(name);
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+            match options.open(&path) {
+                Ok(mut file) => {
+                    // From here on dropping the guard removes the file
+                    let temp = Self { path };
+                    writeln!(file, "{}", initial)
+                        .map_err(|e| format!("could not write {}: {}", temp.path.display(), e))?;
+                    return Ok(temp);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 10 => attempts += 1,
+                Err(e) => return Err(format!("could not create {}: {}", path.display(), e)),
+            }
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Opens `$EDITOR` on a temporary file holding `initial` and returns what was
+/// saved. Lines are joined with spaces, since descriptions are shown on one line.
+///
+/// # Returns
+/// * `Result<String, String>` - The edited text, or why there is none
+pub fn edit_in_editor(initial: &str) -> Result<String, String> {
+    let editor = env::var("EDITOR")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .ok_or("$EDITOR is not set")?;
+
+    let file = TempFile::create(initial)?;
+
+    // $EDITOR may carry arguments, e.g. "code --wait"
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    let status = Command::new(program).args(words).arg(&file.path).status();
+    let contents = fs::read_to_string(&file.path);
+    drop(file);
+
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => return Err(format!("{} exited with {}", program, status)),
+        Err(e) => return Err(format!("could not run {}: {}", program, e)),
+    }
+    let contents = contents.map_err(|e| format!("could not read the edited text: {}", e))?;
+
+    let text = contents.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return Err("the description is empty".to_string());
+    }
+    Ok(text)
+}
+
+/// Like `ask`, but maps an empty answer to `None` for optional fields
+fn ask_optional(input: &mut impl BufRead, output: &mut impl Write, label: &str) -> Option<String> {
+    ask_from(input, output, label).filter(|answer| !answer.is_empty())
+}
+
+/// Walks the user through creating a task one field at a time
+///
+/// # Arguments
+/// * `today` - The date relative due dates like `tomorrow` are resolved against
+///
+/// # Returns
+/// * `Option<AddAnswers>` - `None` if the user gave no description or closed stdin
+pub fn add_wizard(today: NaiveDate) -> Option<AddAnswers> {
+    run_add_wizard(&mut io::stdin().lock(), &mut io::stdout(), today)
+}
+
+/// `add_wizard` reading answers from `input` and prompting on `output`
+fn run_add_wizard(input: &mut impl BufRead, output: &mut impl Write, today: NaiveDate) -> Option<AddAnswers> {
+    let description = ask_from(input, output, "Description: ")?;
+    if description.is_empty() {
+        return None;
+    }
+
+    // Checked here so a typo is asked again instead of failing the whole
+    // wizard once every field has been typed
+    let due = loop {
+        match ask_optional(input, output, "Due date (YYYY-MM-DD, tomorrow, +3d..., blank for none): ") {
+            Some(answer) if types::parse_date(&answer, today).is_none() => {
+                writeln!(output, "'{}' is not a date; use YYYY-MM-DD, 'tomorrow', '+3d' or a weekday", answer).ok()?;
+            }
+            answer => break answer,
+        }
+    };
+
+    let priority = loop {
+        match ask_optional(input, output, "Priority (low/medium/high, blank for none): ") {
+            Some(answer) => match answer.parse::<Priority>() {
+                Ok(p) => break Some(p),
+                Err(e) => writeln!(output, "{}", e).ok()?,
+            },
+            None => break None,
+        }
+    };
+
+    let tags = ask_optional(input, output, "Tags (comma-separated, blank for none): ");
+
+    Some(AddAnswers { description, priority, tags, due })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_file_is_new_private_and_removed_on_drop() {
+        let file = TempFile::create("Buy milk").unwrap();
+        let path = file.path.clone();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Buy milk\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let other = TempFile::create("").unwrap();
+        assert_ne!(other.path, path);
+
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn confirm_needs_an_explicit_yes() {
+        for (answer, expected) in [("y\n", true), ("YES\n", true), ("n\n", false), ("\n", false), ("", false)] {
+            let mut input = io::Cursor::new(answer);
+            assert_eq!(confirm_from(&mut input, &mut io::sink(), "Delete?"), expected, "{:?}", answer);
+        }
+    }
+
+    #[test]
+    fn wizard_collects_each_field() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 11).unwrap();
+        let mut input = io::Cursor::new("Walk dog\ntomorrow\nhigh\npets, daily\n");
+        let answers = run_add_wizard(&mut input, &mut io::sink(), today).unwrap();
+        assert_eq!(answers.description, "Walk dog");
+        assert_eq!(answers.due.as_deref(), Some("tomorrow"));
+        assert_eq!(answers.priority, Some(Priority::High));
+        assert_eq!(answers.tags.as_deref(), Some("pets, daily"));
+    }
+
+    #[test]
+    fn wizard_asks_again_for_a_bad_due_date() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 11).unwrap();
+        let mut input = io::Cursor::new("Walk dog\nsomeday\n2025-07-01\n\n\n");
+        let mut output = Vec::new();
+        let answers = run_add_wizard(&mut input, &mut output, today).unwrap();
+        assert_eq!(answers.due.as_deref(), Some("2025-07-01"));
+        assert_eq!(answers.priority, None);
+        assert!(String::from_utf8(output).unwrap().contains("'someday' is not a date"));
+    }
+
+    #[test]
+    fn wizard_without_a_description_adds_nothing() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 11).unwrap();
+        assert!(run_add_wizard(&mut io::Cursor::new("\n"), &mut io::sink(), today).is_none());
+        assert!(run_add_wizard(&mut io::Cursor::new(""), &mut io::sink(), today).is_none());
+    }
+
+    #[test]
+    fn selection_accepts_numbers_ranges_and_all() {
+        assert_eq!(parse_selection("1, 3", 4), Ok(vec![0, 2]));
+        assert_eq!(parse_selection("2-4 1", 4), Ok(vec![1, 2, 3, 0]));
+        assert_eq!(parse_selection("2,2", 4), Ok(vec![1]));
+        assert_eq!(parse_selection(" A ", 3), Ok(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn selection_rejects_answers_outside_the_menu() {
+        assert!(parse_selection("", 4).is_err());
+        assert!(parse_selection("x", 4).is_err());
+        assert!(parse_selection("0", 4).is_err());
+        assert!(parse_selection("5", 4).is_err());
+        assert!(parse_selection("1-100000", 4).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+// Boolean task queries for `todo find`, e.g. `tag:work AND priority:high OR due:<3d`
+
+use crate::task::Task;
+use crate::types::{self, Priority};
+use chrono::NaiveDate;
+
+/// A single test applied to a task
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Carries this tag (case-insensitive)
+    Tag(String),
+    Priority(Priority),
+    /// Due fewer than this many days from today; overdue tasks count
+    DueWithin(i64),
+    /// Due more than this many days from today
+    DueBeyond(i64),
+    /// Description contains this text (case-insensitive)
+    Text(String),
+}
+
+impl Condition {
+    fn matches(&self, task: &Task, today: NaiveDate) -> bool {
+        let days_until = task.due_date.map(|due| (due - today).num_days());
+        match self {
+            Condition::Tag(tag) => task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            Condition::Priority(priority) => task.priority.as_ref() == Some(priority),
+            Condition::DueWithin(days) => days_until.is_some_and(|d| d < *days),
+            Condition::DueBeyond(days) => days_until.is_some_and(|d| d > *days),
+            Condition::Text(text) => task.matches_keyword(text),
+        }
+    }
+}
+
+/// A parsed query: alternatives joined by `OR`, each a run of conditions joined
+/// by `AND`. `AND` binds tighter, so `a AND b OR c` means `(a AND b) OR c`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    alternatives: Vec<Vec<Condition>>,
+}
+
+impl Query {
+    pub fn matches(&self, task: &Task, today: NaiveDate) -> bool {
+        self.alternatives.iter()
+            .any(|all| all.iter().all(|condition| condition.matches(task, today)))
+    }
+}
+
+/// Parses a query string
+///
+/// Terms are `tag:NAME`, `priority:LEVEL`, `due:<Nd`, `due:>Nd` (`w` for weeks),
+/// `text:WORDS` or a bare word, which is the same as `text:`. Double quotes
+/// group words, as in `text:"buy milk"`. Terms next to each other without an
+/// operator are joined with `AND`.
+///
+/// # Returns
+/// * `Result<Query, String>` - The query, or a message describing the first problem
+pub fn parse(input: &str) -> Result<Query, String> {
+    let mut alternatives = vec![Vec::new()];
+    let mut expecting_term = true;
+
+    for token in tokenize(input)? {
+        match token.as_str() {
+            "AND" | "OR" if expecting_term => {
+                return Err(format!("expected a condition before '{}'", token));
+            }
+            "AND" => expecting_term = true,
+            "OR" => {
+                alternatives.push(Vec::new());
+                expecting_term = true;
+            }
+            _ => {
+                if let Some(all) = alternatives.last_mut() {
+                    all.push(parse_condition(&token)?);
+                }
+                expecting_term = false;
+            }
+        }
+    }
+
+    if expecting_term {
+        return Err(if input.trim().is_empty() {
+            "the query is empty".to_string()
+        } else {
+            "the query ends with an operator".to_string()
+        });
+    }
+    Ok(Query { alternatives })
+}
+
+/// Splits on whitespace, keeping double-quoted text together (quotes removed)
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn parse_condition(term: &str) -> Result<Condition, String> {
+    let Some((field, value)) = term.split_once(':') else {
+        return Ok(Condition::Text(term.to_string()));
+    };
+    if value.is_empty() {
+        return Err(format!("'{}' needs a value", term));
+    }
+
+    match field.to_lowercase().as_str() {
+        "tag" => Ok(Condition::Tag(value.to_string())),
+        "priority" => value.parse().map(Condition::Priority),
+        "text" => Ok(Condition::Text(value.to_string())),
+        "due" => {
+            let (within, days) = match (value.strip_prefix('<'), value.strip_prefix('>')) {
+                (Some(days), _) => (true, days),
+                (_, Some(days)) => (false, days),
+                _ => return Err(format!("'{}' must compare with < or >, e.g. due:<3d", term)),
+            };
+            let days = types::parse_days(days).map_err(|_| format!("invalid number of days in '{}'", term))?;
+            Ok(if within { Condition::DueWithin(days) } else { Condition::DueBeyond(days) })
+        }
+        _ => Err(format!("unknown field '{}', use tag, priority, due or text", field)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(description: &str, priority: Option<Priority>, tags: &[&str], due_in: Option<u64>) -> Task {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 11).unwrap();
+        let due = due_in.and_then(|days| today.checked_add_days(chrono::Days::new(days)));
+        Task::new(1, description.to_string(), priority, tags.iter().map(|tag| tag.to_string()).collect(), due)
+    }
+
+    fn matches(query: &str, task: &Task) -> bool {
+        parse(query).unwrap().matches(task, NaiveDate::from_ymd_opt(2025, 6, 11).unwrap())
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let report = task("Write report", Some(Priority::High), &["work"], Some(1));
+        let errand = task("Buy milk", Some(Priority::Low), &["home"], Some(10));
+
+        assert!(matches("tag:work AND priority:high AND due:<3d", &report));
+        assert!(!matches("tag:work AND priority:low", &report));
+        assert!(matches("tag:work AND priority:low OR text:milk", &errand));
+        assert!(!matches("tag:work AND priority:low OR text:milk", &report));
+        assert!(matches("due:>7d TAG:Home", &errand));
+        assert!(matches(r#"text:"buy milk""#, &errand));
+    }
+
+    #[test]
+    fn parse_rejects_malformed_queries() {
+        for query in ["tag:", "due:3d", "colour:red", "priority:urgent", "tag:work AND", "OR tag:work"] {
+            assert!(parse(query).is_err(), "{}", query);
+        }
+    }
+}
@@ -0,0 +1,540 @@
+// Shared helpers for turning tasks into terminal output
+
+use crate::task::Task;
+use crate::types::{self, AccentColor, Priority};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use clap::builder::styling::{AnsiColor, Style};
+use std::io::IsTerminal;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Gap between table columns
+const COLUMN_GAP: &str = "  ";
+
+/// Descriptions are never cut shorter than this, even on a very narrow terminal
+pub const MIN_DESCRIPTION_WIDTH: usize = 10;
+
+/// Markers used in task output, swappable for terminals that can't show emoji
+#[derive(Debug)]
+pub struct Symbols {
+    pub done: &'static str,
+    pub overdue: &'static str,
+    pub due_today: &'static str,
+    pub due_tomorrow: &'static str,
+    pub due_soon: &'static str,
+    pub high: &'static str,
+    pub medium: &'static str,
+    pub low: &'static str,
+    /// Drawn in a task's label color
+    pub label: &'static str,
+    pub pinned: &'static str,
+    /// Starts the overdue reminder banner
+    pub warning: &'static str,
+}
+
+impl Symbols {
+    /// Marker shown before a priority label; empty when there is none
+    pub fn priority(&self, priority: &Priority) -> &'static str {
+        match priority {
+            Priority::High => self.high,
+            Priority::Medium => self.medium,
+            Priority::Low => self.low,
+        }
+    }
+}
+
+/// The default markers
+pub const EMOJI: Symbols = Symbols {
+    done: "✓",
+    overdue: "🔴",
+    due_today: "🟡",
+    due_tomorrow: "🟠",
+    due_soon: "🟡",
+    high: "🔴",
+    medium: "🟡",
+    low: "🟢",
+    label: "●",
+    pinned: "📌",
+    warning: "⚠",
+};
+
+/// ASCII-only markers for `--plain-emoji`. Priorities go without a marker,
+/// since the label already says it.
+pub const PLAIN: Symbols = Symbols {
+    done: "x",
+    overdue: "[!]",
+    due_today: "[~]",
+    due_tomorrow: "[>]",
+    due_soon: "[~]",
+    high: "",
+    medium: "",
+    low: "",
+    label: "*",
+    pinned: "^",
+    warning: "!",
+};
+
+/// Wraps `text` in the ANSI escape codes for `style`
+fn paint(style: Style, text: &str) -> String {
+    format!("{}{}{}", style.render(), text, style.render_reset())
+}
+
+impl From<AccentColor> for AnsiColor {
+    fn from(color: AccentColor) -> Self {
+        match color {
+            AccentColor::Red => AnsiColor::Red,
+            AccentColor::Green => AnsiColor::Green,
+            AccentColor::Yellow => AnsiColor::Yellow,
+            AccentColor::Blue => AnsiColor::Blue,
+            AccentColor::Magenta => AnsiColor::Magenta,
+            AccentColor::Cyan => AnsiColor::Cyan,
+        }
+    }
+}
+
+/// Renders a heading such as a list title, bold in the list's accent color
+/// when it has one
+pub fn heading(text: &str, accent: Option<AccentColor>) -> String {
+    match accent {
+        Some(accent) => paint(AnsiColor::from(accent).on_default().bold(), text),
+        None => text.to_string(),
+    }
+}
+
+/// Highlights a warning such as an overdue notice in bold red when `color` is set
+pub fn alert(text: &str, color: bool) -> String {
+    if color {
+        paint(AnsiColor::Red.on_default().bold(), text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Style for a task's description: dimmed and struck through when the task
+/// is completed and `strike_completed` is on, as long as colors are enabled
+pub fn description_style(completed: bool, color: bool, strike_completed: bool) -> Option<Style> {
+    (completed && color && strike_completed).then(|| Style::new().dimmed().strikethrough())
+}
+
+/// Renders a task priority for display
+///
+/// # Arguments
+/// * `priority` - The task's priority, if any
+/// * `color` - Add the marker and ANSI colors (same palette as the CLI `STYLES`)
+/// * `symbols` - Where the marker comes from
+///
+/// # Returns
+/// * `String` - `HIGH`/`MED`/`LOW`/`None`, decorated when `color` is set
+pub fn format_priority(priority: &Option<Priority>, color: bool, symbols: &Symbols) -> String {
+    let Some(priority) = priority else {
+        return "None".to_string();
+    };
+
+    if color {
+        let ansi = match priority {
+            Priority::High => AnsiColor::Red,
+            Priority::Medium => AnsiColor::Yellow,
+            Priority::Low => AnsiColor::Green,
+        };
+        let label = paint(ansi.on_default().bold(), priority.label());
+        match symbols.priority(priority) {
+            "" => label,
+            marker => format!("{} {}", marker, label),
+        }
+    } else {
+        priority.label().to_string()
+    }
+}
+
+/// How `format_task_line` lays out a task
+#[derive(Debug, Clone, Copy)]
+pub struct TaskFormat<'a> {
+    /// A single line instead of a block with the priority and tags below
+    pub compact: bool,
+    /// ANSI colors for priorities and overdue notices
+    pub color: bool,
+    /// Shorten the description so the first line fits in this many columns
+    pub width: Option<usize>,
+    /// Show every due date in full with how far off it is, instead of relative notices
+    pub absolute_dates: bool,
+    /// strftime format for due dates, instead of `YYYY-MM-DD`
+    pub date_format: Option<&'a str>,
+    /// De-emphasize completed tasks' descriptions (needs `color`)
+    pub strike_completed: bool,
+    pub symbols: &'static Symbols,
+}
+
+/// Formats `date` with the user's `--date-format`, or `default` without one
+pub fn format_date(date: NaiveDate, date_format: Option<&str>, default: &str) -> String {
+    date.format(date_format.unwrap_or(default)).to_string()
+}
+
+/// Renders a task the way `list`, `search` and `show` print it
+///
+/// # Arguments
+/// * `today` - Reference date for the due-date indicator
+/// * `format` - Compact or block layout, colors and width
+///
+/// # Returns
+/// * `String` - Either `[ ] 3: desc (due 2025-06-01) !HIGH #a,#b`, or a block
+///   whose first line carries the due-date indicator, followed by one line each
+///   for the priority, tags and estimate; blocks end in a newline
+pub fn format_task_line(task: &Task, today: NaiveDate, format: &TaskFormat) -> String {
+    let symbols = format.symbols;
+    let status = if task.completed { symbols.done } else { " " };
+    let mut status_prefix = format!("[{}] {}: ", status, task.id);
+    if task.pinned {
+        status_prefix.push_str(&format!("{} ", symbols.pinned));
+    }
+    // Labels are colors, so without color there is nothing to show
+    let label_dot = match task.label {
+        Some(label) if format.color => format!("{} ", paint(AnsiColor::from(label).on_default(), symbols.label)),
+        _ => String::new(),
+    };
+    // ANSI codes take no columns, so measure the dot on its own
+    let prefix_width = status_prefix.width() + if label_dot.is_empty() { 0 } else { symbols.label.width() + 1 };
+    let prefix = status_prefix + &label_dot;
+
+    if format.compact {
+        let mut suffix = String::new();
+        if let Some(due) = task.due_date {
+            let date = format_date(due, format.date_format, "%Y-%m-%d");
+            // Completed tasks only show their date, as in the block layout below
+            if format.absolute_dates && !task.completed {
+                suffix.push_str(&format!(" (due {}, {})", date, relative_due(due, today)));
+            } else {
+                suffix.push_str(&format!(" (due {})", date));
+            }
+        }
+        if let Some(priority) = &task.priority {
+            suffix.push_str(&format!(" !{}", priority.label()));
+        }
+        if !task.tags.is_empty() {
+            let tags: Vec<String> = task.tags.iter().map(|tag| format!("#{}", tag)).collect();
+            suffix.push_str(&format!(" {}", tags.join(",")));
+        }
+        let description = style_description(task, format, fit_description(&task.description, format.width, prefix_width + suffix.width()));
+        return format!("{}{}{}", prefix, description, suffix);
+    }
+
+    // A completed task can't be late any more, so it just shows its date
+    let urgency_indicator = |color: bool| match task.due_date {
+        Some(due) if task.completed => format!(" (due {})", format_date(due, format.date_format, "%Y-%m-%d")),
+        Some(due) => due_indicator(due, today, format, color),
+        None => String::new(),
+    };
+
+    let priority_display = format_priority(&task.priority, format.color, symbols);
+    let tags_display = if task.tags.is_empty() { "No tags".to_string() } else { task.tags.join(", ") };
+    let estimate_display = task.estimate_minutes
+        .map(|m| format!("  Estimate: {}\n", types::format_duration(m)))
+        .unwrap_or_default();
+
+    // Measure without ANSI codes, which take no columns; the trailing period takes one
+    let used = prefix_width + urgency_indicator(false).width() + 1;
+    let description = style_description(task, format, fit_description(&task.description, format.width, used));
+    format!("{}{}{}.\n  Priority: {}\n  Tags: {}\n{}", prefix, description, urgency_indicator(format.color), priority_display, tags_display, estimate_display)
+}
+
+/// Applies `description_style` to an already shortened description
+fn style_description(task: &Task, format: &TaskFormat, description: String) -> String {
+    match description_style(task.completed, format.color, format.strike_completed) {
+        Some(style) => paint(style, &description),
+        None => description,
+    }
+}
+
+/// The due-date notice after a task's description, with a marker for tasks
+/// that are overdue or due within three days
+///
+/// # Arguments
+/// * `format` - With `absolute_dates`, always show the full date followed by how
+///   far off it is, e.g. `due 2025-06-01 (in 2 days)`, instead of `Due in 2 days`
+///   or `(due 06-01)`; a `date_format` replaces both date layouts
+/// * `color` - Highlight overdue notices
+fn due_indicator(due: NaiveDate, today: NaiveDate, format: &TaskFormat, color: bool) -> String {
+    let symbols = format.symbols;
+    let days_until = (due - today).num_days();
+    let marker = match days_until {
+        ..0 => Some(symbols.overdue),
+        0 => Some(symbols.due_today),
+        1 => Some(symbols.due_tomorrow),
+        2..=3 => Some(symbols.due_soon),
+        _ => None,
+    };
+    let marker = marker.map(|marker| format!(" {}", marker)).unwrap_or_default();
+
+    if format.absolute_dates {
+        let relative = relative_due(due, today);
+        let relative = if days_until < 0 { alert(&relative, color) } else { relative };
+        return format!("{} due {} ({})", marker, format_date(due, format.date_format, "%Y-%m-%d"), relative);
+    }
+
+    match days_until {
+        ..0 => format!("{} {}", marker, alert(&format!("OVERDUE by {} days", -days_until), color)),
+        0 => format!("{} DUE TODAY", marker),
+        1 => format!("{} Due tomorrow", marker),
+        2..=3 => format!("{} Due in {} days", marker, days_until),
+        4..=7 => format!(" (due {})", format_date(due, format.date_format, "%m-%d")),
+        _ => format!(" (due {})", format_date(due, format.date_format, "%Y-%m-%d")),
+    }
+}
+
+/// How far `due` is from `today`, e.g. `in 2 days`, `today` or `3 days overdue`
+pub fn relative_due(due: NaiveDate, today: NaiveDate) -> String {
+    let days_until = (due - today).num_days();
+    let plural = |days: i64| if days == 1 { "" } else { "s" };
+    match days_until {
+        ..0 => format!("{} day{} overdue", -days_until, plural(-days_until)),
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        days => format!("in {} day{}", days, plural(days)),
+    }
+}
+
+/// How long before `now` the moment `at` was, e.g. `45 seconds ago`,
+/// `3 hours ago` or `2 days ago`; times in the future count as `just now`
+pub fn humanize_since(at: DateTime<FixedOffset>, now: DateTime<FixedOffset>) -> String {
+    let elapsed = now.signed_duration_since(at);
+    let (count, unit) = match elapsed.num_seconds() {
+        ..1 => return "just now".to_string(),
+        seconds @ ..60 => (seconds, "second"),
+        _ if elapsed.num_minutes() < 60 => (elapsed.num_minutes(), "minute"),
+        _ if elapsed.num_hours() < 24 => (elapsed.num_hours(), "hour"),
+        _ => (elapsed.num_days(), "day"),
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// Shortens a description to what's left of `width` after `used` columns
+fn fit_description(description: &str, width: Option<usize>, used: usize) -> String {
+    match width {
+        Some(width) => truncate(description, width.saturating_sub(used).max(MIN_DESCRIPTION_WIDTH)),
+        None => description.to_string(),
+    }
+}
+
+/// Width of the terminal stdout is attached to, or `None` when output is
+/// piped or redirected and shouldn't be cut to fit a screen
+pub fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size().ok().map(|(columns, _)| usize::from(columns))
+}
+
+/// Shortens `text` to at most `max_width` terminal columns, ending it with `…`
+/// when anything was cut. Widths are measured in columns, so wide characters
+/// such as emoji count as two.
+pub fn truncate(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+
+    // Leave one column for the ellipsis
+    let mut result = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > max_width - 1 {
+            break;
+        }
+        result.push(c);
+        width += char_width;
+    }
+    result.push('…');
+    result
+}
+
+/// Lays out rows as left-aligned columns, each as wide as its widest cell
+///
+/// # Arguments
+/// * `headers` - Column titles, printed as the first row
+/// * `rows` - Cells for each row, in the same order as `headers`
+///
+/// # Returns
+/// * `String` - One line per row (header included), each ending in a newline
+pub fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.width()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    let header_row: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+    std::iter::once(&header_row)
+        .chain(rows)
+        .map(|row| {
+            let cells: Vec<String> = row.iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{}{}", cell, " ".repeat(width - cell.width())))
+                .collect();
+            cells.join(COLUMN_GAP).trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_priority_is_just_the_label() {
+        assert_eq!(format_priority(&Some(Priority::High), false, &EMOJI), "HIGH");
+        assert_eq!(format_priority(&Some(Priority::Medium), false, &EMOJI), "MED");
+        assert_eq!(format_priority(&Some(Priority::Low), false, &EMOJI), "LOW");
+        assert_eq!(format_priority(&None, false, &EMOJI), "None");
+    }
+
+    #[test]
+    fn colored_priority_gets_the_marker_for_each_level() {
+        for priority in [Priority::High, Priority::Medium, Priority::Low] {
+            let emoji = format_priority(&Some(priority.clone()), true, &EMOJI);
+            assert!(emoji.starts_with(&format!("{} ", EMOJI.priority(&priority))));
+            assert!(emoji.contains(priority.label()));
+
+            let plain = format_priority(&Some(priority.clone()), true, &PLAIN);
+            assert!(plain.starts_with("\x1b["), "{:?}", plain);
+            assert!(plain.contains(priority.label()));
+        }
+    }
+
+    #[test]
+    fn table_columns_fit_their_widest_cell() {
+        let rows = vec![
+            vec!["1".to_string(), "✓".to_string(), "Walk dog".to_string()],
+            vec!["12".to_string(), "🔴".to_string(), "Buy milk and eggs".to_string()],
+        ];
+        let lines: Vec<String> = table(&["ID", "S", "Description"], &rows).lines().map(str::to_string).collect();
+        assert_eq!(lines[0], format!("ID{0}S {0}Description", COLUMN_GAP));
+        assert_eq!(lines[1], format!("1 {0}✓ {0}Walk dog", COLUMN_GAP));
+        assert_eq!(lines[2], format!("12{0}🔴{0}Buy milk and eggs", COLUMN_GAP));
+    }
+
+    #[test]
+    fn truncate_only_cuts_text_wider_than_the_limit() {
+        assert_eq!(truncate("Walk dog", 10), "Walk dog");
+        assert_eq!(truncate("Walk dog", 8), "Walk dog");
+        assert_eq!(truncate("Walk the dog", 8), "Walk th…");
+        // Emoji are two columns wide, so only one fits before the ellipsis
+        assert_eq!(truncate("🐕🐕🐕", 4), "🐕…");
+        assert_eq!(truncate("Walk dog", 0), "");
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// Uncolored output with ASCII markers and no width limit
+    fn plain(compact: bool) -> TaskFormat<'static> {
+        TaskFormat {
+            compact,
+            color: false,
+            width: None,
+            absolute_dates: false,
+            date_format: None,
+            strike_completed: false,
+            symbols: &PLAIN,
+        }
+    }
+
+    fn full_task() -> Task {
+        let tags = vec!["a".to_string(), "b".to_string()];
+        Task::new(3, "Write report".to_string(), Some(Priority::High), tags, Some(date(2025, 6, 1)))
+    }
+
+    #[test]
+    fn compact_line_holds_every_field() {
+        let mut task = full_task();
+        let today = date(2025, 5, 20);
+        assert_eq!(format_task_line(&task, today, &plain(true)), "[ ] 3: Write report (due 2025-06-01) !HIGH #a,#b");
+        task.completed = true;
+        assert_eq!(format_task_line(&task, today, &plain(true)), "[x] 3: Write report (due 2025-06-01) !HIGH #a,#b");
+    }
+
+    #[test]
+    fn block_layout_shows_the_due_notice_and_detail_lines() {
+        let task = full_task();
+        assert_eq!(
+            format_task_line(&task, date(2025, 6, 3), &plain(false)),
+            "[ ] 3: Write report [!] OVERDUE by 2 days.\n  Priority: HIGH\n  Tags: a, b\n"
+        );
+        assert_eq!(
+            format_task_line(&task, date(2025, 5, 30), &plain(false)),
+            "[ ] 3: Write report [~] Due in 2 days.\n  Priority: HIGH\n  Tags: a, b\n"
+        );
+
+        let mut bare = Task::new(4, "Call mom".to_string(), None, Vec::new(), None);
+        bare.estimate_minutes = Some(90);
+        bare.pinned = true;
+        assert_eq!(
+            format_task_line(&bare, date(2025, 6, 3), &plain(false)),
+            "[ ] 4: ^ Call mom.\n  Priority: None\n  Tags: No tags\n  Estimate: 1h 30m\n"
+        );
+    }
+
+    #[test]
+    fn width_shortens_only_the_description() {
+        let format = TaskFormat { width: Some(30), ..plain(true) };
+        let line = format_task_line(&full_task(), date(2025, 5, 20), &format);
+        assert!(line.starts_with("[ ] 3: "), "{}", line);
+        assert!(line.ends_with("… (due 2025-06-01) !HIGH #a,#b"), "{}", line);
+    }
+
+    #[test]
+    fn absolute_dates_pair_the_date_with_how_far_off_it_is() {
+        let format = TaskFormat { absolute_dates: true, ..plain(false) };
+        let first_line = |today| format_task_line(&full_task(), today, &format).lines().next().unwrap().to_string();
+        assert_eq!(first_line(date(2025, 6, 4)), "[ ] 3: Write report [!] due 2025-06-01 (3 days overdue).");
+        assert_eq!(first_line(date(2025, 6, 1)), "[ ] 3: Write report [~] due 2025-06-01 (today).");
+        assert_eq!(first_line(date(2025, 3, 1)), "[ ] 3: Write report due 2025-06-01 (in 92 days).");
+
+        let compact = TaskFormat { absolute_dates: true, ..plain(true) };
+        assert_eq!(
+            format_task_line(&full_task(), date(2025, 5, 31), &compact),
+            "[ ] 3: Write report (due 2025-06-01, tomorrow) !HIGH #a,#b"
+        );
+    }
+
+    #[test]
+    fn humanize_since_picks_the_largest_whole_unit() {
+        let now = DateTime::parse_from_rfc3339("2025-06-10T12:00:00+00:00").unwrap();
+        let ago = |seconds: i64| humanize_since(now - chrono::Duration::seconds(seconds), now);
+        assert_eq!(ago(-30), "just now");
+        assert_eq!(ago(0), "just now");
+        assert_eq!(ago(1), "1 second ago");
+        assert_eq!(ago(59), "59 seconds ago");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3599), "59 minutes ago");
+        assert_eq!(ago(2 * 3600), "2 hours ago");
+        assert_eq!(ago(86_399), "23 hours ago");
+        assert_eq!(ago(86_400), "1 day ago");
+        assert_eq!(ago(45 * 86_400), "45 days ago");
+    }
+
+    #[test]
+    fn only_completed_tasks_with_colors_and_striking_on_are_styled() {
+        assert_eq!(description_style(true, true, true), Some(Style::new().dimmed().strikethrough()));
+        for (completed, color, strike_completed) in [(false, true, true), (true, false, true), (true, true, false)] {
+            assert_eq!(description_style(completed, color, strike_completed), None);
+        }
+    }
+
+    #[test]
+    fn completed_tasks_show_only_their_due_date() {
+        let mut task = full_task();
+        task.completed = true;
+        let today = date(2025, 6, 4);
+        for compact in [false, true] {
+            for absolute_dates in [false, true] {
+                let format = TaskFormat { absolute_dates, ..plain(compact) };
+                let line = format_task_line(&task, today, &format);
+                assert!(line.contains("(due 2025-06-01)"), "{}", line);
+                assert!(!line.to_lowercase().contains("overdue"), "{}", line);
+            }
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use super::{Diagnosis, FormatReport, JournalEvent, StorageBackend, StorageOptions, DATA_VERSION};
+use crate::task::Task;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The data file's top-level layout: `{ "version": 1, "tasks": [...] }`
+#[derive(Serialize, Deserialize)]
+struct DataFile<T> {
+    version: u32,
+    tasks: T,
+}
+
+/// Just the version of a current-layout data file; the tasks are skipped unparsed
+#[derive(Deserialize)]
+struct VersionField {
+    version: u32,
+}
+
+/// Every layout the data file has had. Files written before versioning are a
+/// bare task array and count as version 0; they're rewritten in the current
+/// layout on the next save.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredData {
+    Versioned(DataFile<Vec<Task>>),
+    Legacy(Vec<Task>),
+}
+
+/// Parses the data file's contents in either the current or the legacy layout
+///
+/// # Returns
+/// * `Result<(u32, Vec<Task>), serde_json::Error>` - The file's format version and its tasks
+pub fn parse_data(contents: &str) -> Result<(u32, Vec<Task>), serde_json::Error> {
+    Ok(match serde_json::from_str(contents)? {
+        StoredData::Versioned(data) => (data.version, data.tasks),
+        StoredData::Legacy(tasks) => (0, tasks),
+    })
+}
+
+/// Once the journal holds this many changes, opening the storage folds them
+/// into the data file so loading doesn't slow down
+const JOURNAL_COMPACT_THRESHOLD: usize = 100;
+
+/// Stores tasks in `.todo_data.json`, rewriting the whole file on each save.
+/// With the journal enabled, single-task changes are appended to
+/// `.todo_data.journal` instead and replayed on load.
+pub struct JsonStorage {
+    file_path: PathBuf,
+    verify_checksum: bool,
+    journal: bool,
+    /// There was no data file, so `new` created an empty one
+    created: bool,
+    use_lock: bool,
+}
+
+impl JsonStorage {
+    /// Creates storage for the data file in `directory`
+    pub fn new(directory: &Path, options: StorageOptions) -> Self {
+        let file_path = directory.join(".todo_data.json");
+        let created = !file_path.exists();
+        let storage = Self {
+            file_path,
+            verify_checksum: options.verify_checksum,
+            journal: options.journal,
+            created,
+            use_lock: options.use_lock,
+        };
+
+        let compact = storage.read_journal().len() >= JOURNAL_COMPACT_THRESHOLD;
+        if created || compact {
+            // Both write the data file, so they wait for any other process
+            // that's changing it; the lock is released again right after
+            let _lock = storage.lock();
+
+            // Start with an empty but valid file so `doctor` and `export` have
+            // something to work with before the first task is added
+            if !storage.file_path.exists() {
+                storage.save_tasks(&[]);
+            }
+            // Never compact a file from a newer version that `open` is about to refuse
+            if compact && storage.stored_version().is_none_or(|version| version <= DATA_VERSION) {
+                storage.compact();
+            }
+        }
+        storage
+    }
+
+    /// Loads the tasks in the data file itself, without the journal
+    fn load_data_file(&self) -> Vec<Task> {
+        if let Ok(contents) = fs::read_to_string(&self.file_path) {
+            if self.verify_checksum {
+                match self.checksum_matches(&contents) {
+                    Some(true) => {}
+                    Some(false) => eprintln!(
+                        "Warning: CHECKSUM MISMATCH for {} - the data file was modified outside of todo",
+                        self.file_path.display()
+                    ),
+                    None => eprintln!("Warning: no checksum recorded for {}", self.file_path.display()),
+                }
+            }
+
+            if !self.verify_file_integrity() {
+                eprintln!("Warning: Data file appears to be corrupted or tampered with");
+                return Vec::new();
+            }
+
+            match parse_data(&contents) {
+                Ok((version, tasks)) => {
+                    if version > DATA_VERSION {
+                        eprintln!(
+                            "Warning: {} was written by a newer version of todo (format {}, this build understands {})",
+                            self.file_path.display(), version, DATA_VERSION
+                        );
+                    }
+                    tasks
+                }
+                Err(_) => {
+                    eprintln!("Warning: could not parse tasks file, starting fresh");
+                    Vec::new()
+                }
+            }
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.file_path.with_file_name(".todo_data.journal")
+    }
+
+    /// Reads the journaled changes, oldest first. A line that doesn't parse (such
+    /// as one cut short by a crash) is skipped with a warning.
+    fn read_journal(&self) -> Vec<JournalEvent> {
+        let Ok(contents) = fs::read_to_string(self.journal_path()) else {
+            return Vec::new();
+        };
+        contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    eprintln!("Warning: skipping unreadable journal entry: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn verify_file_integrity(&self) -> bool {
+        // Basic integrity check - ensure file contains valid JSON and has expected structure
+        if let Ok(contents) = fs::read_to_string(&self.file_path)
+            && let Ok((_, tasks)) = parse_data(&contents)
+        {
+            // Additional checks could be added here (e.g., validate task IDs are sequential)
+            return tasks.iter().all(|task| !task.description.is_empty());
+        }
+        false
+    }
+
+    fn archive_path(&self) -> PathBuf {
+        self.file_path.with_file_name("archive.json")
+    }
+
+    fn checksum_path(&self) -> PathBuf {
+        let mut file_name = self.file_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".sha256");
+        self.file_path.with_file_name(file_name)
+    }
+
+    fn checksum(contents: &str) -> String {
+        format!("{:x}", Sha256::digest(contents.as_bytes()))
+    }
+
+    /// Records the checksum in `sha256sum` format so it can also be checked by hand
+    fn write_checksum(&self, contents: &str) {
+        let file_name = self.file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let line = format!("{}  {}\n", Self::checksum(contents), file_name);
+        if let Err(e) = fs::write(self.checksum_path(), line) {
+            eprintln!("Warning: Could not write checksum file: {}", e);
+        }
+    }
+
+    /// Compares the data file contents against the recorded checksum
+    ///
+    /// # Returns
+    /// * `Option<bool>` - `None` if there is no checksum file to compare against
+    fn checksum_matches(&self, contents: &str) -> Option<bool> {
+        let recorded = fs::read_to_string(self.checksum_path()).ok()?;
+        let expected = recorded.split_whitespace().next().unwrap_or_default();
+        Some(expected == Self::checksum(contents))
+    }
+}
+
+impl StorageBackend for JsonStorage {
+    fn path(&self) -> &Path {
+        &self.file_path
+    }
+
+    fn load_tasks(&self) -> Vec<Task> {
+        let mut tasks = self.load_data_file();
+        for event in self.read_journal() {
+            event.apply(&mut tasks);
+        }
+        super::warn_duplicate_ids(&tasks);
+        tasks
+    }
+
+    fn save_tasks(&self, tasks: &[Task]) -> bool {
+        match serde_json::to_string_pretty(&DataFile { version: DATA_VERSION, tasks }) {
+            Ok(json) => {
+                if let Err(e) = super::write_atomically(&self.file_path, &json) {
+                    eprintln!("Warning: Could not save tasks: {}", e);
+                    false
+                } else {
+                    // Always keep the checksum current so turning on verification later
+                    // doesn't flag our own saves as tampering
+                    self.write_checksum(&json);
+                    // The file now holds every change, so replaying the journal again
+                    // would reapply stale ones
+                    if let Err(e) = fs::remove_file(self.journal_path())
+                        && e.kind() != std::io::ErrorKind::NotFound
+                    {
+                        eprintln!("Warning: Could not clear journal: {}", e);
+                    }
+                    true
+                }
+            }
+            Err(e) => {
+                eprintln!("Warning: Could not serialize tasks: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Journaled changes don't touch the data file, so the journal counts too
+    fn modified_time(&self) -> Option<SystemTime> {
+        [&self.file_path, &self.journal_path()].into_iter()
+            .filter_map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+            .max()
+    }
+
+    fn journaling(&self) -> bool {
+        self.journal
+    }
+
+    fn first_run(&self) -> bool {
+        self.created
+    }
+
+    fn append_event(&self, event: &JournalEvent) -> bool {
+        let line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Warning: Could not serialize change: {}", e);
+                return false;
+            }
+        };
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.journal_path())
+            .and_then(|mut journal| writeln!(journal, "{}", line));
+        match written {
+            Ok(()) => {
+                super::set_owner_only(&self.journal_path());
+                true
+            }
+            Err(e) => {
+                eprintln!("Warning: Could not write to journal: {}", e);
+                false
+            }
+        }
+    }
+
+    fn compact(&self) -> bool {
+        if !self.journal_path().exists() {
+            return true;
+        }
+        // A damaged data file loads as an empty list; saving that would lose it
+        if self.file_path.exists() && !self.verify_file_integrity() {
+            eprintln!("Warning: not compacting the journal into a damaged data file");
+            return false;
+        }
+        // Loading replays the journal; saving writes the result and clears it
+        let tasks = self.load_tasks();
+        self.save_tasks(&tasks)
+    }
+
+    fn load_archive(&self) -> Vec<Task> {
+        match fs::read_to_string(self.archive_path()) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Warning: could not parse archive file: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn append_to_archive(&self, tasks: &[Task]) -> bool {
+        let path = self.archive_path();
+        let archive_contents = fs::read_to_string(&path).ok();
+        let mut archived: Vec<Task> = match archive_contents.as_deref().map(serde_json::from_str) {
+            Some(Ok(archived)) => archived,
+            // Refuse to overwrite an archive we can't read rather than lose its contents
+            Some(Err(e)) => {
+                eprintln!("Warning: could not parse archive file, not archiving: {}", e);
+                return false;
+            }
+            None => Vec::new(),
+        };
+        archived.extend(tasks.iter().cloned());
+
+        match serde_json::to_string_pretty(&archived) {
+            Ok(json) => match fs::write(&path, json) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("Warning: Could not save archive: {}", e);
+                    false
+                }
+            },
+            Err(e) => {
+                eprintln!("Warning: Could not serialize archive: {}", e);
+                false
+            }
+        }
+    }
+
+    fn diagnose(&self) -> Diagnosis {
+        let metadata = fs::metadata(&self.file_path).ok();
+        let parsed = fs::read_to_string(&self.file_path)
+            .ok()
+            .and_then(|contents| parse_data(&contents).ok())
+            .map(|(_, tasks)| tasks);
+
+        Diagnosis {
+            path: self.file_path.display().to_string(),
+            exists: metadata.is_some(),
+            size: metadata.map(|m| m.len()),
+            parses: parsed.is_some(),
+            integrity_ok: self.verify_file_integrity(),
+            duplicate_ids: parsed.as_deref().map(super::duplicate_ids).unwrap_or_default(),
+            permissions: super::file_permissions(&self.file_path),
+        }
+    }
+
+    fn inspect_format(&self) -> Option<FormatReport> {
+        let contents = fs::read_to_string(&self.file_path).ok()?;
+        let (version, tasks) = parse_data(&contents).ok()?;
+
+        let raw: serde_json::Value = serde_json::from_str(&contents).ok()?;
+        let raw_tasks = if version == 0 { &raw } else { &raw["tasks"] };
+        let raw_tasks = raw_tasks.as_array()?;
+
+        Some(FormatReport { version, missing_fields: super::missing_fields(raw_tasks, &tasks) })
+    }
+
+    fn lock(&self) -> Option<File> {
+        if self.use_lock { super::acquire_lock(&self.file_path) } else { None }
+    }
+
+    fn stored_version(&self) -> Option<u32> {
+        let contents = fs::read_to_string(&self.file_path).ok()?;
+        match serde_json::from_str::<VersionField>(&contents) {
+            Ok(data) => Some(data.version),
+            Err(_) if contents.trim_start().starts_with('[') => Some(0),
+            Err(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage_with(name: &str, contents: &str) -> (PathBuf, JsonStorage) {
+        let directory = std::env::temp_dir().join(format!("todo-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join(".todo_data.json"), contents).unwrap();
+        let storage = JsonStorage::new(&directory, StorageOptions::default());
+        (directory, storage)
+    }
+
+    #[test]
+    fn stored_version_reads_current_legacy_and_newer_files() {
+        for (name, contents, expected) in [
+            ("current", r#"{"version":1,"tasks":[]}"#, Some(1)),
+            ("legacy", "[]", Some(0)),
+            ("newer", r#"{"version":7,"tasks":[{"future":true}]}"#, Some(7)),
+            ("garbage", "not json", None),
+        ] {
+            let (directory, storage) = storage_with(name, contents);
+            assert_eq!(storage.stored_version(), expected, "{}", name);
+            fs::remove_dir_all(&directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn loads_legacy_arrays_and_saves_the_envelope() {
+        let task = r#"{"id":1,"description":"Walk dog","priority":null,"completed":false,"created_at":"2025-01-01T00:00:00+00:00"}"#;
+        let (version, tasks) = parse_data(&format!("[{}]", task)).unwrap();
+        assert_eq!((version, tasks[0].description.as_str()), (0, "Walk dog"));
+        let (version, tasks) = parse_data(&format!(r#"{{"version":1,"tasks":[{}]}}"#, task)).unwrap();
+        assert_eq!((version, tasks[0].description.as_str()), (1, "Walk dog"));
+
+        let (directory, storage) = storage_with("envelope", &format!("[{}]", task));
+        let tasks = storage.load_tasks();
+        assert_eq!(tasks.len(), 1);
+        storage.save_tasks(&tasks);
+        let saved = fs::read_to_string(directory.join(".todo_data.json")).unwrap();
+        assert_eq!(parse_data(&saved).unwrap().0, DATA_VERSION);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn replaying_the_journal_matches_a_direct_save() {
+        let task = |id: u32, description: &str| Task::new(id, description.to_string(), None, Vec::new(), None);
+        let (journaled_dir, _) = storage_with("journaled", r#"{"version":1,"tasks":[]}"#);
+        let journaled = JsonStorage::new(&journaled_dir, StorageOptions { journal: true, ..StorageOptions::default() });
+        assert!(journaled.save_tasks(&[task(1, "Walk dog"), task(2, "Buy milk")]));
+
+        let mut renamed = task(1, "Walk the dog");
+        renamed.uid = journaled.load_tasks()[0].uid.clone();
+        for event in [JournalEvent::Upsert(renamed), JournalEvent::Remove(2), JournalEvent::Upsert(task(3, "Call mom"))] {
+            assert!(journaled.append_event(&event));
+        }
+
+        let expected = journaled.load_tasks();
+        assert_eq!(expected.iter().map(|task| task.id).collect::<Vec<_>>(), [1, 3]);
+        let (direct_dir, direct) = storage_with("direct", r#"{"version":1,"tasks":[]}"#);
+        assert!(direct.save_tasks(&expected));
+        let as_json = |tasks: &[Task]| serde_json::to_value(tasks).unwrap();
+        assert_eq!(as_json(&direct.load_tasks()), as_json(&expected));
+
+        // Compacting folds the journal in without changing the result
+        assert!(journaled.compact());
+        assert!(!journaled.journal_path().exists());
+        assert_eq!(as_json(&journaled.load_tasks()), as_json(&expected));
+        fs::remove_dir_all(&journaled_dir).unwrap();
+        fs::remove_dir_all(&direct_dir).unwrap();
+    }
+}
@@ -0,0 +1,410 @@
+// Persistence for tasks. `TodoApp` only talks to the `StorageBackend` trait;
+// the JSON file backend is always available and SQLite is an optional feature.
+
+mod json;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use json::{parse_data, JsonStorage};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+use crate::task::Task;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Version of the stored data format. Bump it whenever the format changes so
+/// older data can be recognized and upgraded.
+pub const DATA_VERSION: u32 = 1;
+
+/// Backends `config.toml` can select with `backend = "..."`
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// A single JSON file, rewritten on every save
+    #[default]
+    Json,
+    /// A SQLite database; needs a build with `--features sqlite`
+    Sqlite,
+}
+
+/// Settings shared by every backend
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StorageOptions {
+    /// Check the JSON file's `.sha256` sidecar on load and warn on mismatch
+    pub verify_checksum: bool,
+    /// Lock the data file while changing it, waiting for any other `todo` process
+    pub use_lock: bool,
+    /// Record single-task changes in an append-only journal (JSON backend only)
+    pub journal: bool,
+}
+
+/// One change recorded in the journal, replayed on top of the data file when loading
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalEvent {
+    /// Adds the task, or replaces the task with the same ID
+    Upsert(Task),
+    /// Removes the task with this ID
+    Remove(u32),
+}
+
+impl JournalEvent {
+    /// Applies this change to a loaded task list
+    pub fn apply(self, tasks: &mut Vec<Task>) {
+        match self {
+            JournalEvent::Upsert(task) => match tasks.iter_mut().find(|existing| existing.id == task.id) {
+                Some(existing) => *existing = task,
+                None => tasks.push(task),
+            },
+            JournalEvent::Remove(id) => tasks.retain(|task| task.id != id),
+        }
+    }
+}
+
+/// What the stored data looks like before `migrate` upgrades it
+pub struct FormatReport {
+    /// Format version the data was written with, 0 for the legacy bare array
+    pub version: u32,
+    /// Fields absent from stored tasks, with how many tasks lack each one.
+    /// They load with default values but are only written out on the next save.
+    pub missing_fields: Vec<(String, usize)>,
+}
+
+impl FormatReport {
+    pub fn is_current(&self) -> bool {
+        self.version == DATA_VERSION && self.missing_fields.is_empty()
+    }
+}
+
+/// Snapshot of the data file's health, as reported by `todo doctor`
+pub struct Diagnosis {
+    pub path: String,
+    pub exists: bool,
+    pub size: Option<u64>,
+    pub parses: bool,
+    pub integrity_ok: bool,
+    pub duplicate_ids: Vec<u32>,
+    pub permissions: Option<u32>,
+}
+
+impl Diagnosis {
+    pub fn is_healthy(&self) -> bool {
+        !self.exists || (self.parses && self.integrity_ok && self.duplicate_ids.is_empty())
+    }
+}
+
+/// Where and how tasks are persisted
+pub trait StorageBackend {
+    /// The file holding the tasks
+    fn path(&self) -> &Path;
+
+    /// Loads every task, or an empty list if there is no data yet
+    fn load_tasks(&self) -> Vec<Task>;
+
+    /// Replaces the stored tasks, returning `false` if nothing was saved
+    fn save_tasks(&self, tasks: &[Task]) -> bool;
+
+    /// Loads previously archived tasks, or an empty list if there is no archive yet
+    fn load_archive(&self) -> Vec<Task>;
+
+    /// Appends tasks to the archive, returning `false` if nothing was saved
+    fn append_to_archive(&self, tasks: &[Task]) -> bool;
+
+    /// Inspects the stored data without modifying it
+    fn diagnose(&self) -> Diagnosis;
+
+    /// Compares the stored data with what a save would write now
+    ///
+    /// # Returns
+    /// * `Option<FormatReport>` - `None` if there is no data or it can't be read
+    fn inspect_format(&self) -> Option<FormatReport>;
+
+    /// Format version the stored data was written with, read without loading the tasks
+    ///
+    /// # Returns
+    /// * `Option<u32>` - `None` if there is no data or it can't be read
+    fn stored_version(&self) -> Option<u32>;
+
+    /// Takes the data file lock, waiting for any other `todo` process holding
+    /// it. Hold it from loading the tasks until they're saved; readers don't
+    /// need it, since saves replace the data file in one step.
+    ///
+    /// # Returns
+    /// * `Option<File>` - The lock, released when dropped, or `None` if locking
+    ///   is turned off or failed
+    fn lock(&self) -> Option<File>;
+
+    /// Whether `append_event` is available; when it isn't, every change goes
+    /// through `save_tasks`
+    fn journaling(&self) -> bool {
+        false
+    }
+
+    /// Records a single change without rewriting the stored tasks, returning
+    /// `false` if it couldn't be written
+    fn append_event(&self, _event: &JournalEvent) -> bool {
+        false
+    }
+
+    /// Folds any journaled changes into the stored tasks, returning `false` on failure
+    fn compact(&self) -> bool {
+        true
+    }
+
+    /// Checks that changes can be saved, so a command can refuse up front
+    /// instead of making changes that are then lost
+    ///
+    /// # Returns
+    /// * `io::Result<()>` - Why the data can't be written, if it can't
+    fn check_writable(&self) -> io::Result<()> {
+        check_writable(self.path())
+    }
+
+    /// Whether opening the storage created the data file because there was
+    /// none yet, i.e. this is the first time todo runs for this list
+    fn first_run(&self) -> bool {
+        false
+    }
+
+    /// Last modification time of the data file, if it exists
+    fn modified_time(&self) -> Option<SystemTime> {
+        fs::metadata(self.path())
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Copies the data file next to itself before a risky rewrite
+    ///
+    /// # Returns
+    /// * `io::Result<PathBuf>` - Where the copy was written
+    fn backup(&self, label: &str) -> io::Result<PathBuf> {
+        let mut file_name = self.path().file_name().unwrap_or_default().to_os_string();
+        file_name.push(format!(".{}.bak", label));
+        let path = self.path().with_file_name(file_name);
+        fs::copy(self.path(), &path)?;
+        Ok(path)
+    }
+
+    /// Loads, changes and saves the tasks while holding the data file lock, for
+    /// callers like the TUI that read without locking between changes
+    ///
+    /// # Returns
+    /// * `Option<Vec<Task>>` - The saved tasks, or `None` if the save failed
+    fn update_tasks(&self, change: &mut dyn FnMut(&mut Vec<Task>)) -> Option<Vec<Task>> {
+        let _lock = self.lock();
+        let mut tasks = self.load_tasks();
+        change(&mut tasks);
+        self.save_tasks(&tasks).then_some(tasks)
+    }
+}
+
+/// Opens the configured backend for a task list
+///
+/// # Arguments
+/// * `kind` - Which backend to use
+/// * `options` - Locking, checksum and journal settings
+/// * `list` - Name of a separate task list, or `None` for the default one
+///
+/// # Returns
+/// * `Result<Box<dyn StorageBackend>, String>` - The backend, or why it couldn't be opened
+pub fn open(kind: BackendKind, options: StorageOptions, list: Option<&str>) -> Result<Box<dyn StorageBackend>, String> {
+    let directory = data_directory(list);
+    ensure_directory(&directory);
+
+    let storage: Box<dyn StorageBackend> = match kind {
+        BackendKind::Json => Box::new(JsonStorage::new(&directory, options)),
+        #[cfg(feature = "sqlite")]
+        BackendKind::Sqlite => SqliteStorage::open(&directory, options.use_lock)
+            .map(|storage| Box::new(storage) as Box<dyn StorageBackend>)
+            .map_err(|e| format!("could not open the SQLite database: {}", e))?,
+        #[cfg(not(feature = "sqlite"))]
+        BackendKind::Sqlite => return Err("this build of todo doesn't include the SQLite backend; rebuild with `--features sqlite`".to_string()),
+    };
+
+    // Saving would write the data back in this build's older format, losing
+    // whatever the newer one added
+    if let Some(version) = storage.stored_version()
+        && version > DATA_VERSION
+    {
+        return Err(format!(
+            "{} was written by a newer version of todo (format {}, this build understands {}); upgrade todo to use it",
+            storage.path().display(), version, DATA_VERSION
+        ));
+    }
+    Ok(storage)
+}
+
+/// Whether `name` can be used as a list name. Names become directory names,
+/// so only letters, digits, `-` and `_` are allowed.
+pub fn is_valid_list_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Directory holding a task list's data and its sidecar files
+pub fn data_directory(list: Option<&str>) -> PathBuf {
+    let mut path = if let Some(data_dir) = dirs::data_dir() {
+        // Use system data directory (e.g., ~/.local/share on Linux, ~/Library/Application Support on macOS)
+        data_dir
+    } else if let Some(home_dir) = dirs::home_dir() {
+        // Fallback to home directory
+        home_dir
+    } else {
+        // Last resort - current directory
+        PathBuf::from(".")
+    };
+
+    path.push("todo-cli");
+    // Each named list gets its own directory so its archive, checksum and
+    // lock files don't collide with the default list's
+    if let Some(list) = list {
+        path.push("lists");
+        path.push(list);
+    }
+    path
+}
+
+fn ensure_directory(directory: &Path) {
+    if !directory.exists()
+        && let Err(e) = fs::create_dir_all(directory)
+    {
+        eprintln!("Warning: Could not create data directory: {}", e);
+    }
+}
+
+/// Replaces `path` with `contents` by writing a temporary file beside it and
+/// renaming it over the original, so a reader never sees a half-written file
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+    let temp_path = path.with_file_name(file_name);
+    fs::write(&temp_path, contents)?;
+    set_owner_only(&temp_path);
+    fs::rename(&temp_path, path)
+}
+
+/// Takes an exclusive lock on `.todo_data.lock` next to `data_path`, blocking
+/// until any other process holding it exits. Failing to lock only warns, so a
+/// filesystem without lock support doesn't make the tool unusable.
+fn acquire_lock(data_path: &Path) -> Option<File> {
+    let path = data_path.with_file_name(".todo_data.lock");
+    let file = match File::create(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Warning: Could not create lock file: {}", e);
+            return None;
+        }
+    };
+
+    match file.try_lock() {
+        Ok(()) => Some(file),
+        Err(TryLockError::WouldBlock) => {
+            eprintln!("Waiting for another todo process to finish...");
+            match file.lock() {
+                Ok(()) => Some(file),
+                Err(e) => {
+                    eprintln!("Warning: Could not lock data file: {}", e);
+                    None
+                }
+            }
+        }
+        Err(TryLockError::Error(e)) => {
+            eprintln!("Warning: Could not lock data file: {}", e);
+            None
+        }
+    }
+}
+
+/// Checks that the data file, if it exists, can be opened for writing and
+/// that new files (such as its checksum or a backup) can be created beside it
+fn check_writable(data_path: &Path) -> io::Result<()> {
+    if data_path.exists() {
+        OpenOptions::new().write(true).open(data_path)?;
+    }
+    let probe = data_path.with_file_name(".todo_write_test");
+    File::create(&probe)?;
+    // Another todo process checking at the same time may have removed it already
+    match fs::remove_file(&probe) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Returns every ID that is used by more than one task, in ascending order
+pub fn duplicate_ids(tasks: &[Task]) -> Vec<u32> {
+    let mut ids: Vec<u32> = tasks.iter().map(|task| task.id).collect();
+    ids.sort_unstable();
+    let mut duplicates: Vec<u32> = ids.windows(2)
+        .filter(|pair| pair[0] == pair[1])
+        .map(|pair| pair[0])
+        .collect();
+    duplicates.dedup();
+    duplicates
+}
+
+/// Warns about duplicate IDs in freshly loaded tasks. The data itself is
+/// intact, so it's kept, and the user is pointed at the repair path.
+fn warn_duplicate_ids(tasks: &[Task]) {
+    let duplicates = duplicate_ids(tasks);
+    if !duplicates.is_empty() {
+        let ids: Vec<String> = duplicates.iter().map(|id| id.to_string()).collect();
+        eprintln!(
+            "Warning: duplicate task IDs found ({}); run `todo doctor --repair` to fix them",
+            ids.join(", ")
+        );
+    }
+}
+
+/// Fields a save would write that the stored tasks don't have yet
+///
+/// # Arguments
+/// * `stored` - Each task's JSON exactly as stored
+/// * `tasks` - The same tasks after loading, with defaults filled in
+///
+/// # Returns
+/// * `Vec<(String, usize)>` - Each missing field and how many tasks lack it
+fn missing_fields(stored: &[serde_json::Value], tasks: &[Task]) -> Vec<(String, usize)> {
+    let mut missing: Vec<(String, usize)> = Vec::new();
+    for (stored, task) in stored.iter().zip(tasks) {
+        let Ok(serde_json::Value::Object(current)) = serde_json::to_value(task) else { continue };
+        for field in current.keys().filter(|field| stored.get(field.as_str()).is_none()) {
+            match missing.iter_mut().find(|(name, _)| name == field) {
+                Some((_, count)) => *count += 1,
+                None => missing.push((field.clone(), 1)),
+            }
+        }
+    }
+    missing
+}
+
+/// Unix permission bits of `path`, if it exists and the platform has them
+fn file_permissions(path: &Path) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path)
+            .ok()
+            .map(|metadata| metadata.permissions().mode() & 0o777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Restricts `path` to read/write for its owner only
+fn set_owner_only(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+    }
+}
@@ -0,0 +1,342 @@
+use super::{Diagnosis, FormatReport, StorageBackend, DATA_VERSION};
+use crate::task::Task;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Both tables keep one row per task, in list order. Tasks are stored as JSON
+/// so new fields don't need a schema change; `id` is a column so the database
+/// can be queried by hand, and `uid` so saves can find the row a task is in.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tasks (position INTEGER PRIMARY KEY, id INTEGER NOT NULL, uid TEXT, data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS archive (position INTEGER PRIMARY KEY, id INTEGER NOT NULL, data TEXT NOT NULL);
+";
+
+/// Run after `SCHEMA`, once databases created before the `uid` column have it
+const UID_INDEX: &str = "CREATE UNIQUE INDEX IF NOT EXISTS tasks_uid ON tasks (uid)";
+
+/// Stores tasks in a SQLite database, `todo.db`
+pub struct SqliteStorage {
+    db_path: PathBuf,
+    connection: Connection,
+    /// There was no database, so `open` created it
+    created: bool,
+    use_lock: bool,
+}
+
+impl SqliteStorage {
+    /// Opens (creating if needed) the database in `directory`
+    ///
+    /// # Arguments
+    /// * `use_lock` - Lock the database while changing it, waiting for any other `todo` process
+    pub fn open(directory: &Path, use_lock: bool) -> rusqlite::Result<Self> {
+        let db_path = directory.join("todo.db");
+        // Held only while the schema is set up, so two first runs can't both add the uid column
+        let _lock = if use_lock { super::acquire_lock(&db_path) } else { None };
+        let created = !db_path.exists();
+        let connection = Connection::open(&db_path)?;
+        connection.execute_batch(SCHEMA)?;
+        // Older databases lack the uid column. Their rows start without uids and
+        // are rewritten with them by the next save.
+        let has_uid: bool = connection.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('tasks') WHERE name = 'uid'", [], |row| row.get(0),
+        )?;
+        if !has_uid {
+            connection.execute("ALTER TABLE tasks ADD COLUMN uid TEXT", [])?;
+        }
+        connection.execute(UID_INDEX, [])?;
+        // A fresh database starts at version 0; it's created in the current format
+        let version: u32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version == 0 {
+            connection.pragma_update(None, "user_version", DATA_VERSION)?;
+        }
+        super::set_owner_only(&db_path);
+        Ok(Self { db_path, connection, created, use_lock })
+    }
+
+    /// Reads every row of `table` as raw JSON, in list order
+    fn read_rows(&self, table: &str) -> rusqlite::Result<Vec<String>> {
+        let mut statement = self.connection.prepare(&format!("SELECT data FROM {} ORDER BY position", table))?;
+        let rows = statement.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    fn read_tasks(&self, table: &str) -> Result<Vec<Task>, String> {
+        self.read_rows(table)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|data| serde_json::from_str(data).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    /// Inserts `tasks` after the existing rows of `table`, in one transaction
+    fn append_tasks(&self, table: &str, tasks: &[Task]) -> Result<(), String> {
+        let transaction = self.connection.unchecked_transaction().map_err(|e| e.to_string())?;
+        {
+            let mut insert = transaction
+                .prepare(&format!("INSERT INTO {} (id, data) VALUES (?1, ?2)", table))
+                .map_err(|e| e.to_string())?;
+            for task in tasks {
+                let data = serde_json::to_string(task).map_err(|e| e.to_string())?;
+                insert.execute(params![task.id, data]).map_err(|e| e.to_string())?;
+            }
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    /// Makes the tasks table hold `tasks`, in one transaction, writing only the
+    /// rows that changed. Rows are matched to tasks by uid: changed tasks are
+    /// updated in place, new ones inserted and the rest deleted. A row keeps its
+    /// position while it's still in list order; rows that fall out of order move
+    /// after the last one, so reordering costs a write per moved task.
+    fn replace_tasks(&self, tasks: &[Task]) -> rusqlite::Result<()> {
+        let transaction = self.connection.unchecked_transaction()?;
+        let mut stored: HashMap<String, (i64, String)> = HashMap::new();
+        let mut next_position = 0;
+        {
+            let mut select = transaction.prepare("SELECT position, uid, data FROM tasks")?;
+            let mut rows = select.query([])?;
+            while let Some(row) = rows.next()? {
+                let position: i64 = row.get(0)?;
+                next_position = next_position.max(position + 1);
+                if let Some(uid) = row.get::<_, Option<String>>(1)? {
+                    stored.insert(uid, (position, row.get(2)?));
+                }
+            }
+        }
+        // Rows without a uid can't be matched, so they're written afresh
+        transaction.execute("DELETE FROM tasks WHERE uid IS NULL", [])?;
+
+        let mut last_position = -1;
+        for task in tasks {
+            let data = serde_json::to_string(task).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+            match task.uid.as_ref().and_then(|uid| stored.remove(uid)) {
+                Some((position, stored_data)) if position > last_position => {
+                    if stored_data != data {
+                        transaction.execute("UPDATE tasks SET id = ?1, data = ?2 WHERE position = ?3", params![task.id, data, position])?;
+                    }
+                    last_position = position;
+                }
+                Some((position, _)) => {
+                    transaction.execute(
+                        "UPDATE tasks SET position = ?1, id = ?2, data = ?3 WHERE position = ?4",
+                        params![next_position, task.id, data, position],
+                    )?;
+                    last_position = next_position;
+                    next_position += 1;
+                }
+                None => {
+                    transaction.execute(
+                        "INSERT INTO tasks (position, id, uid, data) VALUES (?1, ?2, ?3, ?4)",
+                        params![next_position, task.id, task.uid, data],
+                    )?;
+                    last_position = next_position;
+                    next_position += 1;
+                }
+            }
+        }
+
+        // Whatever wasn't matched is no longer in the list
+        for (position, _) in stored.into_values() {
+            transaction.execute("DELETE FROM tasks WHERE position = ?1", params![position])?;
+        }
+        transaction.commit()
+    }
+}
+
+impl StorageBackend for SqliteStorage {
+    fn path(&self) -> &Path {
+        &self.db_path
+    }
+
+    fn first_run(&self) -> bool {
+        self.created
+    }
+
+    fn load_tasks(&self) -> Vec<Task> {
+        match self.read_tasks("tasks") {
+            Ok(tasks) => {
+                super::warn_duplicate_ids(&tasks);
+                tasks
+            }
+            Err(e) => {
+                eprintln!("Warning: could not read tasks from {}: {}", self.db_path.display(), e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn save_tasks(&self, tasks: &[Task]) -> bool {
+        match self.replace_tasks(tasks) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Warning: Could not save tasks: {}", e);
+                false
+            }
+        }
+    }
+
+    fn load_archive(&self) -> Vec<Task> {
+        self.read_tasks("archive").unwrap_or_else(|e| {
+            eprintln!("Warning: could not read archive: {}", e);
+            Vec::new()
+        })
+    }
+
+    fn append_to_archive(&self, tasks: &[Task]) -> bool {
+        match self.append_tasks("archive", tasks) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Warning: Could not save archive: {}", e);
+                false
+            }
+        }
+    }
+
+    fn diagnose(&self) -> Diagnosis {
+        let metadata = fs::metadata(&self.db_path).ok();
+        let parsed = self.read_tasks("tasks").ok();
+        let sqlite_ok = self.connection
+            .query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0))
+            .is_ok_and(|result| result == "ok");
+
+        Diagnosis {
+            path: self.db_path.display().to_string(),
+            exists: metadata.is_some(),
+            size: metadata.map(|m| m.len()),
+            parses: parsed.is_some(),
+            integrity_ok: sqlite_ok
+                && parsed.as_ref().is_some_and(|tasks| tasks.iter().all(|task| !task.description.is_empty())),
+            duplicate_ids: parsed.as_deref().map(super::duplicate_ids).unwrap_or_default(),
+            permissions: super::file_permissions(&self.db_path),
+        }
+    }
+
+    fn inspect_format(&self) -> Option<FormatReport> {
+        let version = self.stored_version()?;
+        let rows = self.read_rows("tasks").ok()?;
+        let stored: Vec<serde_json::Value> = rows.iter()
+            .map(|data| serde_json::from_str(data))
+            .collect::<Result<_, _>>()
+            .ok()?;
+        let tasks = self.read_tasks("tasks").ok()?;
+        Some(FormatReport { version, missing_fields: super::missing_fields(&stored, &tasks) })
+    }
+
+    fn lock(&self) -> Option<File> {
+        if self.use_lock { super::acquire_lock(&self.db_path) } else { None }
+    }
+
+    fn stored_version(&self) -> Option<u32> {
+        self.connection.query_row("PRAGMA user_version", [], |row| row.get(0)).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("todo-test-sqlite-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn tasks(descriptions: &[&str]) -> Vec<Task> {
+        descriptions.iter()
+            .enumerate()
+            .map(|(index, description)| Task::new(index as u32 + 1, description.to_string(), None, Vec::new(), None))
+            .collect()
+    }
+
+    fn descriptions(storage: &SqliteStorage) -> Vec<String> {
+        storage.load_tasks().into_iter().map(|task| task.description).collect()
+    }
+
+    /// Each row's uid and position, in list order
+    fn positions(storage: &SqliteStorage) -> Vec<(String, i64)> {
+        let mut select = storage.connection.prepare("SELECT uid, position FROM tasks ORDER BY position").unwrap();
+        select.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn save_rewrites_only_changed_rows() {
+        let directory = scratch_dir("changed");
+        let storage = SqliteStorage::open(&directory, false).unwrap();
+        let mut list = tasks(&["Walk dog", "Buy milk", "Pay rent"]);
+        assert!(storage.save_tasks(&list));
+        let before = positions(&storage);
+
+        list.remove(1);
+        list[1].description = "Pay rent today".to_string();
+        list.extend(tasks(&["Call mom"]));
+        assert!(storage.save_tasks(&list));
+
+        assert_eq!(descriptions(&storage), ["Walk dog", "Pay rent today", "Call mom"]);
+        let after = positions(&storage);
+        assert_eq!(after[0], before[0]);
+        assert_eq!(after[1], before[2]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn save_keeps_a_reordered_list_in_order() {
+        let directory = scratch_dir("reordered");
+        let storage = SqliteStorage::open(&directory, false).unwrap();
+        let mut list = tasks(&["Walk dog", "Buy milk", "Pay rent"]);
+        assert!(storage.save_tasks(&list));
+
+        list.rotate_left(1);
+        assert!(storage.save_tasks(&list));
+        assert_eq!(descriptions(&storage), ["Buy milk", "Pay rent", "Walk dog"]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn open_adds_the_uid_column_to_older_databases() {
+        let directory = scratch_dir("older");
+        {
+            let connection = Connection::open(directory.join("todo.db")).unwrap();
+            connection.execute_batch("
+                CREATE TABLE tasks (position INTEGER PRIMARY KEY, id INTEGER NOT NULL, data TEXT NOT NULL);
+                PRAGMA user_version = 1;
+            ").unwrap();
+            let data = serde_json::to_string(&tasks(&["Walk dog"])[0]).unwrap();
+            connection.execute("INSERT INTO tasks (id, data) VALUES (1, ?1)", params![data]).unwrap();
+        }
+
+        let storage = SqliteStorage::open(&directory, false).unwrap();
+        let mut list = storage.load_tasks();
+        list.extend(tasks(&["Buy milk"]));
+        list[1].id = 2;
+        assert!(storage.save_tasks(&list));
+        assert_eq!(descriptions(&storage), ["Walk dog", "Buy milk"]);
+        assert_eq!(positions(&storage).len(), 2);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn tasks_survive_reopening_the_database() {
+        let directory = scratch_dir("reopen");
+        let mut list = tasks(&["Walk dog", "Buy milk"]);
+        list[0].priority = Some(crate::types::Priority::High);
+        list[0].tags = vec!["pets".to_string()];
+        list[1].complete(chrono::Local::now().fixed_offset());
+        assert!(SqliteStorage::open(&directory, false).unwrap().save_tasks(&list));
+
+        let storage = SqliteStorage::open(&directory, false).unwrap();
+        let mut loaded = storage.load_tasks();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&list).unwrap());
+
+        // Removing and adding are both persisted
+        loaded.remove(0);
+        loaded.extend(tasks(&["Call mom"]));
+        loaded[1].id = 3;
+        assert!(storage.save_tasks(&loaded));
+        let reopened = SqliteStorage::open(&directory, false).unwrap();
+        assert_eq!(descriptions(&reopened), ["Buy milk", "Call mom"]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
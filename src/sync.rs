@@ -0,0 +1,111 @@
+// `todo sync`: commits the data directory and syncs it with its git remote
+
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+/// The lock file changes on every run and means nothing on another machine
+const LOCK_FILE_PATHSPEC: &str = ":(exclude).todo_data.lock";
+
+/// Arguments for staging every change in the data directory except the lock file
+pub fn add_args() -> Vec<&'static str> {
+    vec!["add", "--all", "--", ".", LOCK_FILE_PATHSPEC]
+}
+
+/// Arguments for committing what `add_args` staged
+pub fn commit_args(message: &str) -> Vec<&str> {
+    vec!["commit", "--quiet", "-m", message]
+}
+
+/// Arguments for fetching other machines' changes, replaying ours on top
+pub fn pull_args() -> Vec<&'static str> {
+    vec!["pull", "--rebase", "--quiet"]
+}
+
+pub fn push_args() -> Vec<&'static str> {
+    vec!["push", "--quiet"]
+}
+
+/// Commits local changes, pulls with rebase and pushes
+///
+/// # Arguments
+/// * `directory` - The data directory, which must be inside a git repository
+/// * `message` - Commit message for local changes
+///
+/// # Returns
+/// * `Result<bool, String>` - Whether there were local changes to commit, or
+///   what went wrong
+pub fn sync(directory: &Path, message: &str) -> Result<bool, String> {
+    if git(directory, &["rev-parse", "--is-inside-work-tree"]).is_err() {
+        return Err(format!(
+            "{} is not in a git repository; run `git init` there and add a remote first",
+            directory.display()
+        ));
+    }
+
+    git(directory, &add_args())?;
+    let committed = !git(directory, &["diff", "--cached", "--name-only"])?.trim().is_empty();
+    if committed {
+        git(directory, &commit_args(message))?;
+    }
+
+    git(directory, &pull_args()).map_err(|e| {
+        if e.contains("CONFLICT") || e.contains("could not apply") {
+            format!(
+                "changes from the remote conflict with yours; resolve them in {} with git (see `git status`), then run `todo sync` again",
+                directory.display()
+            )
+        } else {
+            e
+        }
+    })?;
+    git(directory, &push_args())?;
+    Ok(committed)
+}
+
+/// Runs git in `directory`
+///
+/// # Returns
+/// * `Result<String, String>` - What git printed to stdout, or why it failed
+fn git(directory: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => "git is not installed or not on your PATH".to_string(),
+            _ => format!("could not run git: {}", e),
+        })?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        // Conflict reports go to stdout, most other errors to stderr
+        let details: Vec<String> = [&output.stdout, &output.stderr].into_iter()
+            .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect();
+        Err(format!("`git {}` failed: {}", args.join(" "), details.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_stages_everything_but_the_lock_file() {
+        assert_eq!(add_args(), ["add", "--all", "--", ".", ":(exclude).todo_data.lock"]);
+    }
+
+    #[test]
+    fn commit_passes_the_message_as_one_argument() {
+        assert_eq!(commit_args("Sync tasks; rm -rf ~"), ["commit", "--quiet", "-m", "Sync tasks; rm -rf ~"]);
+    }
+
+    #[test]
+    fn pull_rebases_and_push_is_quiet() {
+        assert_eq!(pull_args(), ["pull", "--rebase", "--quiet"]);
+        assert_eq!(push_args(), ["push", "--quiet"]);
+    }
+}
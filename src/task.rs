@@ -1,8 +1,41 @@
-use chrono::{DateTime, Local, NaiveDate};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use crate::types::Priority;
+use crate::types::{AccentColor, Priority};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Characters uids are made of: lowercase RFC 4648 base32
+const UID_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Length of a generated uid; 32^4 is about a million possibilities
+const UID_LENGTH: usize = 4;
+
+/// Makes a random uid such as `k3xq`. It always contains a letter, so it can't
+/// be mistaken for a numeric task ID on the command line. Callers that need it
+/// unique within a list should check for clashes and generate another.
+pub fn generate_uid() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        // RandomState is seeded randomly per process; mixing in the time and a
+        // counter gives a fresh value on every call
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+        let mut bits = RandomState::new().hash_one((nanos, COUNTER.fetch_add(1, Ordering::Relaxed)));
+        let uid: String = (0..UID_LENGTH)
+            .map(|_| {
+                let c = UID_ALPHABET[(bits % 32) as usize] as char;
+                bits /= 32;
+                c
+            })
+            .collect();
+        if uid.chars().any(|c| c.is_ascii_alphabetic()) {
+            return uid;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: u32,
     pub description: String,
@@ -11,7 +44,21 @@ pub struct Task {
     pub tags: Vec<String>,
     pub completed: bool,
     pub due_date: Option<NaiveDate>,
-    pub completed_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub estimate_minutes: Option<u32>,
+    /// Color label for grouping tasks visually, independent of priority
+    #[serde(default)]
+    pub label: Option<AccentColor>,
+    /// Listed above unpinned tasks whatever the sort order
+    #[serde(default)]
+    pub pinned: bool,
+    /// Short random identifier that, unlike `id`, is never reused; tasks from
+    /// before uids existed get one from `todo migrate`
+    #[serde(default)]
+    pub uid: Option<String>,
+    /// Stored with its UTC offset so timestamps keep their meaning when the data
+    /// file moves between machines or timezones
+    pub completed_at: Option<DateTime<FixedOffset>>,
 }
 
 impl Task {
@@ -23,46 +70,232 @@ impl Task {
         due_date: Option<NaiveDate>
         ) -> Self {
         
-        Self {
+        let mut task = Self {
             id,
             description,
             completed: false,
             priority,
             tags,
             due_date,
+            estimate_minutes: None,
+            label: None,
+            pinned: false,
+            uid: Some(generate_uid()),
             completed_at: None,
-        }
+        };
+        task.sort_tags();
+        task
     }
 
-    pub fn complete(&mut self) {
+    /// Puts the tags in alphabetical order (ignoring case) and drops exact
+    /// repeats, so they display the same however they were entered
+    pub fn sort_tags(&mut self) {
+        self.tags.sort_by_cached_key(|tag| tag.to_lowercase());
+        self.tags.dedup();
+    }
+
+    /// Marks the task complete at `now`, which carries the offset to store it with
+    pub fn complete(&mut self, now: DateTime<FixedOffset>) {
         self.completed = true;
-        self.completed_at = Some(Local::now());
+        self.completed_at = Some(now);
     }
 
-    pub fn is_urgent(&self, days_threshold: i64) -> bool {
-        if self.completed {
+    /// Marks a completed task as not done again
+    pub fn reopen(&mut self) {
+        self.completed = false;
+        self.completed_at = None;
+    }
+
+    /// Rewrites the completion timestamp with a UTC offset, keeping the same instant
+    ///
+    /// # Returns
+    /// * `bool` - Whether the stored timestamp changed
+    pub fn convert_to_utc(&mut self) -> bool {
+        match self.completed_at {
+            Some(at) if at.offset().local_minus_utc() != 0 => {
+                self.completed_at = Some(at.with_timezone(&Utc).fixed_offset());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Lowercases the tags and drops any that repeat
+    ///
+    /// # Returns
+    /// * `bool` - Whether the tags changed
+    pub fn normalize_tags(&mut self) -> bool {
+        let original = self.tags.clone();
+        for tag in self.tags.iter_mut() {
+            *tag = tag.to_lowercase();
+        }
+        self.sort_tags();
+        self.tags != original
+    }
+
+    /// Whether an active task is due within `days_threshold` days of `today`.
+    /// Overdue tasks count as urgent however long ago they were due, since
+    /// they need attention at least as much as tasks due soon. Completed tasks
+    /// never do unless `include_completed` is set, e.g. for auditing.
+    pub fn is_urgent(&self, today: NaiveDate, days_threshold: i64, include_completed: bool) -> bool {
+        if self.completed && !include_completed {
             return false;
         }
         
-        self.due_date.map_or(false, |due| {
-            let today = Local::now().date_naive();
+        self.due_date.is_some_and(|due| {
             let days_until_due = (due - today).num_days();
             days_until_due <= days_threshold
         })
     }
 
-    pub fn is_overdue(&self) -> bool {
+    /// How pressing an active task is, combining its due date and priority;
+    /// higher is more urgent. Overdue tasks outrank anything merely due soon,
+    /// and within the same due date a higher priority wins.
+    pub fn urgency_score(&self, today: NaiveDate) -> i64 {
         if self.completed {
+            return 0;
+        }
+
+        let due_score = match self.due_date.map(|due| (due - today).num_days()) {
+            // The longer overdue, the more urgent, up to a point
+            Some(days) if days < 0 => 100 + 5 * (-days).min(20),
+            // 70 today, 60 tomorrow, fading out after a week
+            Some(days) => (70 - 10 * days).max(0),
+            None => 0,
+        };
+        let priority_score = match self.priority {
+            Some(Priority::High) => 30,
+            Some(Priority::Medium) => 20,
+            Some(Priority::Low) => 10,
+            None => 0,
+        };
+        due_score + priority_score
+    }
+
+    /// Whether the task's due date is before `today`; completed tasks never
+    /// are unless `include_completed` is set
+    pub fn is_overdue(&self, today: NaiveDate, include_completed: bool) -> bool {
+        if self.completed && !include_completed {
             return false;
         }
         
-        self.due_date.map_or(false, |due| {
-            let today = Local::now().date_naive();
-            due < today
-        })
+        self.due_date.is_some_and(|due| due < today)
     }
 
     pub fn matches_keyword(&self, keyword: &str) -> bool {
         self.description.to_lowercase().contains(&keyword.to_lowercase())
     }
-}
\ No newline at end of file
+
+    /// Exact-case variant of `matches_keyword`
+    pub fn matches_keyword_cased(&self, keyword: &str) -> bool {
+        self.description.contains(keyword)
+    }
+
+    pub fn matches_regex(&self, pattern: &Regex) -> bool {
+        pattern.is_match(&self.description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn due_on(due: NaiveDate, priority: Option<Priority>) -> Task {
+        Task::new(1, "Walk dog".to_string(), priority, Vec::new(), Some(due))
+    }
+
+    #[test]
+    fn urgent_means_due_within_the_threshold_or_overdue() {
+        let today = date(2025, 6, 11);
+        assert!(due_on(date(2025, 6, 14), None).is_urgent(today, 3, false));
+        assert!(!due_on(date(2025, 6, 15), None).is_urgent(today, 3, false));
+        assert!(due_on(date(2025, 1, 1), None).is_urgent(today, 3, false));
+        let undated = Task::new(2, "Someday".to_string(), None, Vec::new(), None);
+        assert!(!undated.is_urgent(today, 3, false));
+    }
+
+    #[test]
+    fn completed_tasks_are_only_urgent_or_overdue_when_included() {
+        let today = date(2025, 6, 11);
+        let mut task = due_on(date(2025, 6, 1), None);
+        task.completed = true;
+        assert!(!task.is_urgent(today, 3, false));
+        assert!(task.is_urgent(today, 3, true));
+        assert!(!task.is_overdue(today, false));
+        assert!(task.is_overdue(today, true));
+        assert_eq!(task.urgency_score(today), 0);
+    }
+
+    #[test]
+    fn due_today_is_not_overdue() {
+        let today = date(2025, 6, 11);
+        assert!(!due_on(today, None).is_overdue(today, false));
+        assert!(due_on(date(2025, 6, 10), None).is_overdue(today, false));
+    }
+
+    #[test]
+    fn urgency_score_ranks_overdue_then_sooner_then_priority() {
+        let today = date(2025, 6, 11);
+        let overdue = due_on(date(2025, 6, 10), None).urgency_score(today);
+        let due_today = due_on(today, Some(Priority::High)).urgency_score(today);
+        let tomorrow_high = due_on(date(2025, 6, 12), Some(Priority::High)).urgency_score(today);
+        let tomorrow_low = due_on(date(2025, 6, 12), Some(Priority::Low)).urgency_score(today);
+        assert!(overdue > due_today);
+        assert!(due_today > tomorrow_high);
+        assert!(tomorrow_high > tomorrow_low);
+        // Long-overdue tasks stop gaining urgency after 20 days
+        assert_eq!(due_on(date(2025, 5, 1), None).urgency_score(today), due_on(date(2025, 5, 2), None).urgency_score(today));
+    }
+
+    #[test]
+    fn overdue_high_outranks_due_tomorrow_low() {
+        let today = date(2025, 6, 11);
+        let overdue_high = due_on(date(2025, 6, 10), Some(Priority::High));
+        let tomorrow_low = due_on(date(2025, 6, 12), Some(Priority::Low));
+        assert!(overdue_high.urgency_score(today) > tomorrow_low.urgency_score(today));
+    }
+
+    #[test]
+    fn utc_conversion_keeps_the_completion_instant() {
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let done = tokyo.with_ymd_and_hms(2025, 6, 11, 8, 30, 0).unwrap();
+        let mut task = due_on(date(2025, 6, 11), None);
+        task.complete(done);
+
+        assert!(task.convert_to_utc());
+        let stored = task.completed_at.unwrap();
+        assert_eq!(stored.offset().local_minus_utc(), 0);
+        assert_eq!(stored, done);
+        assert_eq!(stored.to_rfc3339(), "2025-06-10T23:30:00+00:00");
+
+        // Read back in another zone, it's still the same moment
+        let new_york = FixedOffset::west_opt(4 * 3600).unwrap();
+        let round_tripped: Task = serde_json::from_str(&serde_json::to_string(&task).unwrap()).unwrap();
+        assert_eq!(round_tripped.completed_at.unwrap().with_timezone(&new_york).to_rfc3339(), "2025-06-10T19:30:00-04:00");
+        assert!(!task.convert_to_utc());
+    }
+
+    #[test]
+    fn keyword_matching_ignores_case_unless_asked() {
+        let task = Task::new(1, "Buy milk".to_string(), None, Vec::new(), None);
+        assert!(task.matches_keyword("buy"));
+        assert!(task.matches_keyword("MILK"));
+        assert!(!task.matches_keyword_cased("buy"));
+        assert!(task.matches_keyword_cased("Buy"));
+    }
+
+    #[test]
+    fn generated_uids_are_short_base32_with_a_letter() {
+        for _ in 0..1000 {
+            let uid = generate_uid();
+            assert_eq!(uid.len(), UID_LENGTH);
+            assert!(uid.bytes().all(|c| UID_ALPHABET.contains(&c)), "{}", uid);
+            assert!(uid.chars().any(|c| c.is_ascii_alphabetic()), "{}", uid);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Reusable task blueprints for `todo template`, one JSON file per template in
+// the `templates` directory next to the default list's data
+
+use crate::storage;
+use crate::types::Priority;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The fields a new task copies from a template. Due dates are given when
+/// the template is used, since a fixed date would soon be in the past.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    pub description: String,
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub estimate_minutes: Option<u32>,
+}
+
+/// Templates are shared by every list
+fn directory() -> PathBuf {
+    storage::data_directory(None).join("templates")
+}
+
+fn path(name: &str) -> PathBuf {
+    directory().join(format!("{}.json", name))
+}
+
+/// Writes a template, replacing any existing one with the same name
+pub fn save(name: &str, template: &Template) -> io::Result<PathBuf> {
+    fs::create_dir_all(directory())?;
+    let json = serde_json::to_string_pretty(template)?;
+    let path = path(name);
+    fs::write(&path, json)?;
+    Ok(path)
+}
+
+/// Reads a template
+///
+/// # Returns
+/// * `io::Result<Option<Template>>` - `None` if no template has that name
+pub fn load(name: &str) -> io::Result<Option<Template>> {
+    match fs::read_to_string(path(name)) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Names of all saved templates, alphabetically
+pub fn names() -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(directory()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json")
+            && let Some(name) = path.file_stem()
+        {
+            names.push(name.to_string_lossy().to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
@@ -0,0 +1,180 @@
+// Conversion between tasks and the todo.txt format (https://github.com/todotxt/todo.txt)
+//
+// Mapping:
+// - priority: High/Medium/Low <-> (A)/(B)/(C); (D) and below import as Low
+// - completed: leading `x`, followed by the completion date
+// - tags: exported as `+tag`; both `+project` and `@context` import as tags
+// - due date: `due:YYYY-MM-DD`
+
+use crate::task::Task;
+use crate::types::Priority;
+use chrono::{Local, NaiveDate, TimeZone};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+fn priority_letter(priority: &Priority) -> char {
+    match priority {
+        Priority::High => 'A',
+        Priority::Medium => 'B',
+        Priority::Low => 'C',
+    }
+}
+
+fn priority_from_letter(letter: char) -> Option<Priority> {
+    match letter {
+        'A' => Some(Priority::High),
+        'B' => Some(Priority::Medium),
+        'C'..='Z' => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// Parses a `(A)` style priority token
+fn parse_priority_token(token: &str) -> Option<Priority> {
+    let mut chars = token.chars();
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some('('), Some(letter), Some(')'), None) => priority_from_letter(letter),
+        _ => None,
+    }
+}
+
+fn parse_date(token: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(token, DATE_FORMAT).ok()
+}
+
+/// Renders a single task as one todo.txt line
+pub fn format_task(task: &Task) -> String {
+    let mut parts = Vec::new();
+
+    if task.completed {
+        parts.push("x".to_string());
+        if let Some(done) = task.completed_at {
+            parts.push(done.with_timezone(&Local).format(DATE_FORMAT).to_string());
+        }
+    } else if let Some(p) = &task.priority {
+        parts.push(format!("({})", priority_letter(p)));
+    }
+
+    parts.push(task.description.clone());
+    parts.extend(task.tags.iter().map(|tag| format!("+{}", tag)));
+
+    if let Some(due) = task.due_date {
+        parts.push(format!("due:{}", due.format(DATE_FORMAT)));
+    }
+    // The spec drops the (A) prefix on completion; keep the priority as a key:value
+    // so it survives a round trip
+    if task.completed && let Some(p) = &task.priority {
+        parts.push(format!("pri:{}", priority_letter(p)));
+    }
+
+    parts.join(" ")
+}
+
+/// Parses one todo.txt line into a task with the given ID
+///
+/// # Returns
+/// * `Option<Task>` - `None` for blank lines or lines with no description
+pub fn parse_line(line: &str, id: u32) -> Option<Task> {
+    let mut tokens = line.split_whitespace().peekable();
+
+    let completed = tokens.next_if_eq(&"x").is_some();
+    let mut completed_on = None;
+    if completed {
+        completed_on = tokens.peek().and_then(|t| parse_date(t));
+        if completed_on.is_some() {
+            tokens.next();
+        }
+    }
+
+    let mut priority = tokens.peek().and_then(|t| parse_priority_token(t));
+    if priority.is_some() {
+        tokens.next();
+    }
+
+    // Creation date, which tasks don't track
+    if tokens.peek().and_then(|t| parse_date(t)).is_some() {
+        tokens.next();
+    }
+
+    let mut description = Vec::new();
+    let mut tags = Vec::new();
+    let mut due_date = None;
+
+    for token in tokens {
+        if let Some(tag) = token.strip_prefix('+').or_else(|| token.strip_prefix('@'))
+            && !tag.is_empty()
+        {
+            tags.push(tag.to_string());
+        } else if let Some(date) = token.strip_prefix("due:").and_then(parse_date) {
+            due_date = Some(date);
+        } else if let Some(letter) = token.strip_prefix("pri:")
+            && let Some(p) = letter.chars().next().and_then(priority_from_letter)
+        {
+            priority = Some(p);
+        } else {
+            description.push(token);
+        }
+    }
+
+    if description.is_empty() {
+        return None;
+    }
+
+    let mut task = Task::new(id, description.join(" "), priority, tags, due_date);
+    if completed {
+        task.completed = true;
+        task.completed_at = Some(
+            completed_on
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .and_then(|naive| Local.from_local_datetime(&naive).single())
+                .unwrap_or_else(Local::now)
+                .fixed_offset(),
+        );
+    }
+    Some(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(task: &Task) -> Task {
+        parse_line(&format_task(task), task.id).unwrap()
+    }
+
+    #[test]
+    fn active_task_survives_a_round_trip() {
+        let due = NaiveDate::from_ymd_opt(2025, 6, 11);
+        let task = Task::new(4, "Walk the dog".to_string(), Some(Priority::Medium), vec!["home".to_string(), "pets".to_string()], due);
+        assert_eq!(format_task(&task), "(B) Walk the dog +home +pets due:2025-06-11");
+
+        let parsed = round_trip(&task);
+        assert_eq!(parsed.description, task.description);
+        assert_eq!(parsed.priority, task.priority);
+        assert_eq!(parsed.tags, task.tags);
+        assert_eq!(parsed.due_date, task.due_date);
+        assert!(!parsed.completed);
+    }
+
+    #[test]
+    fn completed_task_keeps_its_date_and_priority() {
+        let mut task = Task::new(1, "Pay rent".to_string(), Some(Priority::High), Vec::new(), None);
+        let done = Local.with_ymd_and_hms(2025, 6, 10, 18, 30, 0).unwrap().fixed_offset();
+        task.complete(done);
+        assert_eq!(format_task(&task), "x 2025-06-10 Pay rent pri:A");
+
+        let parsed = round_trip(&task);
+        assert!(parsed.completed);
+        assert_eq!(parsed.completed_at.map(|at| at.date_naive()), Some(done.date_naive()));
+        assert_eq!(parsed.priority, Some(Priority::High));
+    }
+
+    #[test]
+    fn contexts_import_as_tags_and_blank_lines_are_skipped() {
+        let task = parse_line("(D) 2025-01-01 Call mom @phone +family", 2).unwrap();
+        assert_eq!(task.description, "Call mom");
+        assert_eq!(task.priority, Some(Priority::Low));
+        assert_eq!(task.tags, ["family", "phone"]);
+        assert!(parse_line("   ", 3).is_none());
+    }
+}
@@ -2,15 +2,505 @@
 // For now, our main types are in their respective modules:
 // - Task: src/task.rs
 // - CLI types: src/cli.rs
-// - Storage: src/storage.rs
+// - Storage: src/storage/
 // - App: src/app.rs
 
-use clap::ValueEnum;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ValueEnum)]
+/// Variants are declared lowest first so the derived ordering ranks High above Low
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Priority {
     Low,
     Medium,
     High,
-}
\ No newline at end of file
+}
+
+impl Priority {
+    /// Short uppercase label used wherever a priority is displayed
+    pub fn label(&self) -> &'static str {
+        match self {
+            Priority::High => "HIGH",
+            Priority::Medium => "MED",
+            Priority::Low => "LOW",
+        }
+    }
+}
+
+/// Accepts the canonical names plus the shorthands people actually type:
+/// `h`/`hi`/`3`, `m`/`med`/`mid`/`2` and `l`/`lo`/`1`, in any case.
+/// Used by clap for `--priority` and by anything else that parses user input.
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "high" | "hi" | "h" | "3" => Ok(Priority::High),
+            "medium" | "med" | "mid" | "m" | "2" => Ok(Priority::Medium),
+            "low" | "lo" | "l" | "1" => Ok(Priority::Low),
+            _ => Err(format!("unknown priority '{}', use high, medium or low", input)),
+        }
+    }
+}
+
+/// A task named on the command line: a numeric ID, or else the start of its uid
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskRef {
+    Id(u32),
+    Uid(String),
+}
+
+/// Used by clap for the task arguments of `show`, `edit`, `complete`, `pin`,
+/// `unpin` and `remove`
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        if let Ok(id) = input.parse() {
+            return Ok(TaskRef::Id(id));
+        }
+        if input.is_empty() || !input.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("invalid task '{}', use an ID such as 4 or the start of a uid such as k3x", input));
+        }
+        Ok(TaskRef::Uid(input.to_lowercase()))
+    }
+}
+
+/// Orderings offered by `list --sort`
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// Soonest due date first, undated tasks last
+    #[default]
+    Due,
+    /// Task ID, ascending
+    Id,
+    /// Highest priority first, ties broken by due date
+    Priority,
+    /// Description, alphabetically (case-insensitive)
+    Description,
+    /// Most urgent first, weighing how soon a task is due against its priority
+    Urgency,
+}
+
+/// Colors `list_colors` in `config.toml` can give a task list, also used for task labels
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccentColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl AccentColor {
+    /// Lowercase name, as written in the config and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            AccentColor::Red => "red",
+            AccentColor::Green => "green",
+            AccentColor::Yellow => "yellow",
+            AccentColor::Blue => "blue",
+            AccentColor::Magenta => "magenta",
+            AccentColor::Cyan => "cyan",
+        }
+    }
+}
+
+/// What `add` does when the list already holds `max_tasks` tasks
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitAction {
+    /// Refuse to add the task
+    #[default]
+    Refuse,
+    /// Add it anyway, printing a warning
+    Warn,
+}
+
+/// When `--color` decorates output with ANSI colors and emoji
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Only when stdout is a terminal
+    #[default]
+    Auto,
+    /// Even when output is piped or redirected
+    Always,
+    /// Plain text
+    Never,
+}
+
+impl ColorChoice {
+    /// Resolves `Auto` against whether stdout is a terminal
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => std::io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Ways `list --group-by` can bucket tasks
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// One group per tag; a task with several tags appears in each
+    Tag,
+    /// One group per priority level, highest first
+    Priority,
+}
+
+/// Spans of time `report` can summarize, each ending today
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum ReportPeriod {
+    /// Monday of this week through today
+    #[default]
+    Week,
+    /// The first of this month through today
+    Month,
+}
+
+impl ReportPeriod {
+    /// First day of the period that contains `today`
+    pub fn start(self, today: NaiveDate) -> NaiveDate {
+        match self {
+            ReportPeriod::Week => today - Duration::days(today.weekday().num_days_from_monday() as i64),
+            ReportPeriod::Month => today.with_day(1).unwrap_or(today),
+        }
+    }
+}
+
+/// Result of running a command, mapped to the process exit code in `main`
+///
+/// The codes are part of the CLI contract so scripts can tell outcomes apart:
+/// * `0` - success
+/// * `1` - the referenced task was not found
+/// * `2` - invalid input (e.g. a malformed date)
+/// * `3` - the data file could not be read or written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    NotFound,
+    BadInput,
+    IoError,
+}
+
+impl Outcome {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Success => 0,
+            Outcome::NotFound => 1,
+            Outcome::BadInput => 2,
+            Outcome::IoError => 3,
+        }
+    }
+
+    pub fn is_success(self) -> bool {
+        self == Outcome::Success
+    }
+}
+
+/// Parses a time estimate such as `45m`, `2h`, `1h30m` or a bare number of minutes
+///
+/// # Returns
+/// * `Result<u32, String>` - The estimate in minutes, or a message suitable for clap
+pub fn parse_duration(input: &str) -> Result<u32, String> {
+    let input = input.trim().to_lowercase();
+    let invalid = || format!("invalid duration '{}', use e.g. 45m, 2h or 1h30m", input);
+
+    if let Ok(minutes) = input.parse::<u32>() {
+        return Ok(minutes);
+    }
+
+    let mut total: u32 = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: u32 = number.parse().map_err(|_| invalid())?;
+        let minutes = match c {
+            'h' => value.checked_mul(60).ok_or_else(invalid)?,
+            'm' => value,
+            _ => return Err(invalid()),
+        };
+        total = total.checked_add(minutes).ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || input.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Longest span `parse_days` accepts, about a hundred years
+pub const MAX_DAYS: i64 = 36_500;
+
+/// Parses a span of days such as `3`, `3d` or `2w`, up to `MAX_DAYS` either way
+///
+/// # Returns
+/// * `Result<i64, String>` - The span in days, or a message suitable for clap
+pub fn parse_days(input: &str) -> Result<i64, String> {
+    let invalid = || format!("invalid number of days '{}', use e.g. 3, 3d or 2w", input);
+    let (number, unit) = match input.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (input.strip_suffix('d').unwrap_or(input), 1),
+    };
+    let days = number.parse::<i64>().ok().and_then(|n| n.checked_mul(unit)).ok_or_else(invalid)?;
+    if days.abs() > MAX_DAYS {
+        return Err(format!("'{}' is too many days, the most is {}", input, MAX_DAYS));
+    }
+    Ok(days)
+}
+
+/// Parses a task ID or an inclusive range of IDs such as `3-7`
+///
+/// # Returns
+/// * `Result<RangeInclusive<u32>, String>` - The IDs, or a message suitable for clap
+pub fn parse_id_range(input: &str) -> Result<RangeInclusive<u32>, String> {
+    let parse_id = |id: &str| id.trim().parse::<u32>()
+        .map_err(|_| format!("invalid task ID '{}', use e.g. 4 or 3-7", input));
+
+    match input.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (parse_id(start)?, parse_id(end)?);
+            if start > end {
+                return Err(format!("range '{}' is reversed, use {}-{}", input, end, start));
+            }
+            Ok(start..=end)
+        }
+        None => parse_id(input).map(|id| id..=id),
+    }
+}
+
+/// Flattens parsed ID ranges into IDs, in the order given, dropping repeats
+///
+/// # Arguments
+/// * `ranges` - The ranges from `parse_id_range`
+/// * `highest` - The highest ID there is; a range spanning more IDs than this
+///   can't be meant and is refused, which also keeps typos like `1-50000` cheap
+///
+/// # Returns
+/// * `Result<Vec<u32>, String>` - The IDs, or why a range was refused
+pub fn expand_ids(ranges: Vec<RangeInclusive<u32>>, highest: u32) -> Result<Vec<u32>, String> {
+    if let Some(range) = ranges.iter().find(|range| range.end() - range.start() >= highest.max(1)) {
+        return Err(format!(
+            "range {}-{} covers more IDs than there are tasks, the highest ID is {}",
+            range.start(), range.end(), highest
+        ));
+    }
+
+    let mut seen = HashSet::new();
+    Ok(ranges.into_iter()
+        .flatten()
+        .filter(|id| seen.insert(*id))
+        .collect())
+}
+
+/// Checks a strftime format for showing dates, such as `%d/%m/%Y`. Time
+/// fields like `%H` are refused, since dates have no time to show.
+///
+/// # Returns
+/// * `Result<String, String>` - The format, or a message suitable for clap
+pub fn parse_date_format(input: &str) -> Result<String, String> {
+    let mut sample = String::new();
+    if input.trim().is_empty() || write!(sample, "{}", NaiveDate::default().format(input)).is_err() {
+        return Err(format!("invalid date format '{}', use strftime fields such as %d/%m/%Y", input));
+    }
+    Ok(input.to_string())
+}
+
+/// Formats minutes as a compact duration, e.g. `90` -> `1h 30m`
+pub fn format_duration(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+/// Parses a user-entered date, relative to `today`
+///
+/// Accepted forms:
+/// * `YYYY-MM-DD`
+/// * `YYYY-Www` - an ISO 8601 week (e.g. `2025-W25`), resolved to its Monday
+/// * `today`, `tomorrow`, `yesterday`
+/// * `+N` / `+Nd` / `+Nw` - N days or weeks from today
+/// * `-N` / `-Nd` / `-Nw` - N days or weeks ago
+/// * a weekday name (`friday`, `fri`) - the next such day after today
+pub fn parse_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    if let Some((year, week)) = input.split_once("-w") {
+        // Weeks belong to the ISO week-numbering year, so 2025-W01 starts on 2024-12-30
+        return NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon);
+    }
+
+    match input.as_str() {
+        "today" => return Some(today),
+        "tomorrow" => return Some(today + Duration::days(1)),
+        "yesterday" => return Some(today - Duration::days(1)),
+        _ => {}
+    }
+
+    let (sign, offset) = match (input.strip_prefix('+'), input.strip_prefix('-')) {
+        (Some(offset), _) => (1, Some(offset)),
+        (_, Some(offset)) => (-1, Some(offset)),
+        _ => (1, None),
+    };
+    if let Some(offset) = offset {
+        let (number, unit_days) = match offset.strip_suffix('w') {
+            Some(weeks) => (weeks, 7),
+            None => (offset.strip_suffix('d').unwrap_or(offset), 1),
+        };
+        let count: i64 = number.parse().ok()?;
+        // Offsets too large for a date are invalid input, not a reason to panic
+        return today.checked_add_signed(Duration::try_days(count.checked_mul(unit_days * sign)?)?);
+    }
+
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        let days_ahead = (7 + weekday.num_days_from_monday() as i64
+            - today.weekday().num_days_from_monday() as i64 - 1) % 7 + 1;
+        return Some(today + Duration::days(days_ahead));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn priority_accepts_shorthands_in_any_case() {
+        for input in ["high", "HI", "h", "3", " High "] {
+            assert_eq!(input.parse::<Priority>(), Ok(Priority::High), "{}", input);
+        }
+        for input in ["medium", "med", "Mid", "m", "2"] {
+            assert_eq!(input.parse::<Priority>(), Ok(Priority::Medium), "{}", input);
+        }
+        for input in ["low", "LO", "l", "1"] {
+            assert_eq!(input.parse::<Priority>(), Ok(Priority::Low), "{}", input);
+        }
+        assert!("urgent".parse::<Priority>().is_err());
+        assert!("4".parse::<Priority>().is_err());
+    }
+
+    #[test]
+    fn parse_date_accepts_day_and_week_offsets() {
+        let today = date(2025, 6, 11);
+        assert_eq!(parse_date("+3", today), Some(date(2025, 6, 14)));
+        assert_eq!(parse_date("+3d", today), Some(date(2025, 6, 14)));
+        assert_eq!(parse_date("+2w", today), Some(date(2025, 6, 25)));
+        assert_eq!(parse_date("-1d", today), Some(date(2025, 6, 10)));
+        assert_eq!(parse_date("-2w", today), Some(date(2025, 5, 28)));
+    }
+
+    #[test]
+    fn parse_date_rejects_offsets_out_of_range() {
+        let today = date(2025, 6, 11);
+        assert_eq!(parse_date("+99999999999999d", today), None);
+        assert_eq!(parse_date("-99999999999999d", today), None);
+        assert_eq!(parse_date("+9999999999999999w", today), None);
+        assert_eq!(parse_date("+999999999999999999999d", today), None);
+    }
+
+    #[test]
+    fn parse_days_accepts_days_and_weeks() {
+        assert_eq!(parse_days("3"), Ok(3));
+        assert_eq!(parse_days("3d"), Ok(3));
+        assert_eq!(parse_days("2w"), Ok(14));
+        assert_eq!(parse_days("-1w"), Ok(-7));
+        assert!(parse_days("3x").is_err());
+        assert!(parse_days("").is_err());
+    }
+
+    #[test]
+    fn parse_days_rejects_spans_over_the_limit() {
+        assert_eq!(parse_days(&format!("{}d", MAX_DAYS)), Ok(MAX_DAYS));
+        assert!(parse_days(&format!("{}d", MAX_DAYS + 1)).is_err());
+        assert!(parse_days("9999999999999d").is_err());
+        assert!(parse_days("9999999999999999999w").is_err());
+    }
+
+    #[test]
+    fn parse_id_range_accepts_ids_and_ranges() {
+        assert_eq!(parse_id_range("4"), Ok(4..=4));
+        assert_eq!(parse_id_range("3-7"), Ok(3..=7));
+        assert_eq!(parse_id_range(" 3 - 7 "), Ok(3..=7));
+        assert!(parse_id_range("7-3").unwrap_err().contains("reversed"));
+        assert!(parse_id_range("x").is_err());
+        assert!(parse_id_range("3-").is_err());
+    }
+
+    #[test]
+    fn expand_ids_keeps_order_and_drops_repeats() {
+        assert_eq!(expand_ids(vec![5..=5, 1..=3, 2..=4], 10), Ok(vec![5, 1, 2, 3, 4]));
+        assert_eq!(expand_ids(vec![], 10), Ok(vec![]));
+    }
+
+    #[test]
+    fn expand_ids_refuses_ranges_wider_than_the_list() {
+        assert_eq!(expand_ids(vec![1..=10], 10), Ok((1..=10).collect()));
+        assert!(expand_ids(vec![1..=11], 10).is_err());
+        assert!(expand_ids(vec![1..=50_000], 12).is_err());
+        // A single ID is always allowed, and reported missing later if it is
+        assert_eq!(expand_ids(vec![99..=99], 0), Ok(vec![99]));
+    }
+
+    #[test]
+    fn parse_date_accepts_named_days_and_iso_weeks() {
+        let today = date(2025, 6, 11); // a Wednesday
+        assert_eq!(parse_date("today", today), Some(today));
+        assert_eq!(parse_date("Tomorrow", today), Some(date(2025, 6, 12)));
+        assert_eq!(parse_date("friday", today), Some(date(2025, 6, 13)));
+        assert_eq!(parse_date("wed", today), Some(date(2025, 6, 18)));
+        assert_eq!(parse_date("2025-W01", today), Some(date(2024, 12, 30)));
+        assert_eq!(parse_date("2025-13-01", today), None);
+    }
+
+    #[test]
+    fn iso_weeks_resolve_to_their_monday() {
+        let today = date(2025, 6, 11);
+        assert_eq!(parse_date("2025-W25", today), Some(date(2025, 6, 16)));
+        assert_eq!(parse_date("2026-W01", today), Some(date(2025, 12, 29)));
+        assert_eq!(parse_date("2027-W01", today), Some(date(2027, 1, 4)));
+        assert_eq!(parse_date("2026-W53", today), Some(date(2026, 12, 28)));
+        assert_eq!(parse_date("2025-W53", today), None);
+        assert_eq!(parse_date("2025-W00", today), None);
+    }
+
+    #[test]
+    fn parse_duration_reads_hours_and_minutes() {
+        assert_eq!(parse_duration("45"), Ok(45));
+        assert_eq!(parse_duration("45m"), Ok(45));
+        assert_eq!(parse_duration("2h"), Ok(120));
+        assert_eq!(parse_duration("1H30M"), Ok(90));
+        assert_eq!(parse_duration(" 90 "), Ok(90));
+    }
+
+    #[test]
+    fn parse_duration_rejects_malformed_and_overflowing_input() {
+        for input in ["", "h", "2x", "1h30", "1h 30m", "99999999999h"] {
+            assert!(parse_duration(input).is_err(), "{}", input);
+        }
+    }
+}
@@ -1,17 +1,350 @@
-use std::{io, thread, time::Duration};
+use std::{io, time::{Duration, Instant, SystemTime}};
 use tui::{
-    backend::CrosstermBackend,
-    widgets::{Widget, Block, Borders},
-    layout::{Layout, Constraint, Direction},
-    Terminal
+    backend::{Backend, CrosstermBackend},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    layout::{Layout, Constraint, Direction, Rect},
+    Frame, Terminal
 };
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use crate::render;
+use crate::storage::StorageBackend;
+use crate::task::Task;
 
-pub fn run_ui() -> Result<(), io::Error> {
+/// Everything the TUI needs between redraws
+struct UiState {
+    tasks: Vec<Task>,
+    list_state: ListState,
+    /// Modification time of the data file when `tasks` was loaded
+    last_seen: Option<SystemTime>,
+    /// Where the task list was last drawn, including its border
+    list_area: Rect,
+    /// Index of the first visible row, kept in step with what `List` renders
+    offset: usize,
+    /// Time and row of the previous left click, for detecting double-clicks
+    last_click: Option<(Instant, usize)>,
+    /// Store completion times in UTC rather than local time
+    utc: bool,
+    /// Show completed tasks dimmed and struck through
+    strike_completed: bool,
+    /// Only tasks matching this text are shown; empty shows everything
+    filter: String,
+    /// Keystrokes go to the filter box instead of moving the selection
+    editing_filter: bool,
+}
+
+/// Two clicks on the same row closer together than this count as a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+impl UiState {
+    fn new(tasks: Vec<Task>, last_seen: Option<SystemTime>, utc: bool, strike_completed: bool) -> Self {
+        let mut list_state = ListState::default();
+        if !tasks.is_empty() {
+            list_state.select(Some(0));
+        }
+        Self {
+            tasks,
+            list_state,
+            last_seen,
+            list_area: Rect::default(),
+            offset: 0,
+            last_click: None,
+            utc,
+            strike_completed,
+            filter: String::new(),
+            editing_filter: false,
+        }
+    }
+
+    /// Tasks that pass the current filter, in the order they are drawn
+    fn visible_tasks(&self) -> Vec<&Task> {
+        filter_tasks(&self.tasks, &self.filter)
+    }
+
+    fn select_next(&mut self) {
+        let len = self.visible_tasks().len();
+        if len == 0 {
+            return;
+        }
+        let next = self.list_state.selected().map_or(0, |i| (i + 1).min(len - 1));
+        self.list_state.select(Some(next));
+    }
+
+    fn select_previous(&mut self) {
+        let previous = self.list_state.selected().map_or(0, |i| i.saturating_sub(1));
+        if !self.visible_tasks().is_empty() {
+            self.list_state.select(Some(previous));
+        }
+    }
+
+    /// Number of task rows that fit inside the list's border
+    fn visible_rows(&self) -> usize {
+        usize::from(self.list_area.height.saturating_sub(2))
+    }
+
+    /// Moves the selection a full screen down, stopping at the last task
+    fn page_down(&mut self) {
+        let len = self.visible_tasks().len();
+        if len == 0 {
+            return;
+        }
+        let step = self.visible_rows().max(1);
+        let next = self.list_state.selected().map_or(0, |i| (i + step).min(len - 1));
+        self.list_state.select(Some(next));
+    }
+
+    /// Moves the selection a full screen up, stopping at the first task
+    fn page_up(&mut self) {
+        if self.visible_tasks().is_empty() {
+            return;
+        }
+        let step = self.visible_rows().max(1);
+        let previous = self.list_state.selected().map_or(0, |i| i.saturating_sub(step));
+        self.list_state.select(Some(previous));
+    }
+
+    /// Keeps the selection on a valid row after the task list changes size
+    fn clamp_selection(&mut self) {
+        let selected = match (self.visible_tasks().len(), self.list_state.selected()) {
+            (0, _) => None,
+            (len, Some(i)) => Some(i.min(len - 1)),
+            (_, None) => Some(0),
+        };
+        self.list_state.select(selected);
+    }
+
+    /// Applies a key press while the filter box has focus
+    fn edit_filter(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => self.filter.push(c),
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Enter => self.editing_filter = false,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.editing_filter = false;
+            }
+            _ => return,
+        }
+        // The matching set changed, so start again from the top
+        self.offset = 0;
+        self.list_state.select(None);
+        self.clamp_selection();
+    }
+
+    /// Selects the row under a left click, toggling the task's completion on a double-click
+    fn click(&mut self, storage: &dyn StorageBackend, column: u16, row: u16) {
+        let Some(index) = index_at(self.list_area, self.offset, column, row)
+            .filter(|&index| index < self.visible_tasks().len())
+        else {
+            return;
+        };
+        self.list_state.select(Some(index));
+
+        let now = Instant::now();
+        let double = matches!(self.last_click, Some((at, last)) if last == index && now - at <= DOUBLE_CLICK);
+        if double {
+            self.last_click = None;
+            self.toggle_completed(storage, index);
+        } else {
+            self.last_click = Some((now, index));
+        }
+    }
+
+    fn toggle_completed(&mut self, storage: &dyn StorageBackend, index: usize) {
+        let Some(id) = self.visible_tasks().get(index).map(|task| task.id) else { return };
+        let now: DateTime<FixedOffset> = if self.utc {
+            Utc::now().fixed_offset()
+        } else {
+            Local::now().fixed_offset()
+        };
+        let saved = storage.update_tasks(&mut |tasks| {
+            if let Some(task) = tasks.iter_mut().find(|task| task.id == id) {
+                if task.completed {
+                    task.reopen();
+                } else {
+                    task.complete(now);
+                }
+            }
+        });
+        if let Some(tasks) = saved {
+            self.tasks = tasks;
+            self.last_seen = storage.modified_time();
+            self.clamp_selection();
+        }
+    }
+}
+
+/// Tasks whose description contains `query`, ignoring case; an empty query matches all
+fn filter_tasks<'a>(tasks: &'a [Task], query: &str) -> Vec<&'a Task> {
+    tasks.iter()
+        .filter(|task| query.is_empty() || task.matches_keyword(query))
+        .collect()
+}
+
+/// First visible row once `selected` is scrolled into a window of `height` rows,
+/// matching how `List` picks its offset for single-line items
+fn follow_selection(offset: usize, selected: Option<usize>, height: usize) -> usize {
+    match selected {
+        Some(selected) if selected < offset => selected,
+        Some(selected) if height > 0 && selected >= offset + height => selected + 1 - height,
+        _ => offset,
+    }
+}
+
+/// Maps a terminal cell to the task index drawn there
+///
+/// # Arguments
+/// * `area` - The list's area, including its one-cell border
+/// * `offset` - Index of the first visible task
+///
+/// # Returns
+/// * `Option<usize>` - The task index, or `None` if the cell is outside the rows
+fn index_at(area: Rect, offset: usize, column: u16, row: u16) -> Option<usize> {
+    let inside_columns = column > area.x && column + 1 < area.x + area.width;
+    let inside_rows = row > area.y && row + 1 < area.y + area.height;
+    if !inside_columns || !inside_rows {
+        return None;
+    }
+    Some(offset + usize::from(row - area.y - 1))
+}
+
+/// Re-reads the task list if the data file changed since `last_seen`
+///
+/// # Returns
+/// * `Option<Vec<Task>>` - The new tasks, or `None` if the file is unchanged
+fn reload_if_changed(storage: &dyn StorageBackend, last_seen: &mut Option<SystemTime>) -> Option<Vec<Task>> {
+    let modified = storage.modified_time();
+    if modified == *last_seen {
+        return None;
+    }
+    *last_seen = modified;
+    Some(storage.load_tasks())
+}
+
+fn task_line(task: &Task) -> String {
+    let status = if task.completed { "✓" } else { " " };
+    let due = task.due_date.map(|d| format!(" (due {})", d)).unwrap_or_default();
+    format!("[{}] {}: {}{}", status, task.id, task.description, due)
+}
+
+fn draw<B: Backend>(f: &mut Frame<B>, state: &mut UiState, interval: Duration) {
+    let mut size: tui::layout::Rect = f.size();
+    let block = Block::default()
+        .title("Todo List (q to quit, / to filter, double-click to toggle done)")
+        .borders(Borders::ALL);
+    // render outer "Todo List" block and create an inner area for tasks
+    f.render_widget(block, size);
+    // Only make room for the filter box while it's in use
+    let show_filter = state.editing_filter || !state.filter.is_empty();
+    let constraints = if show_filter {
+        vec![Constraint::Min(0), Constraint::Length(3)]
+    } else {
+        vec![Constraint::Min(0)]
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(constraints)
+        .split(size);
+    size = chunks[0];
+
+    if show_filter {
+        let cursor = if state.editing_filter { "_" } else { "" };
+        let filter_box = Paragraph::new(format!("{}{}", state.filter, cursor))
+            .block(Block::default()
+                .title("Filter (Enter to keep, Esc to clear)")
+                .borders(Borders::ALL));
+        f.render_widget(filter_box, chunks[1]);
+    }
+
+    state.list_area = size;
+    let rows = state.visible_rows();
+    state.offset = follow_selection(state.offset, state.list_state.selected(), rows);
+
+    let mut title = format!("Tasks (refreshing every {}s)", interval.as_secs());
+    let visible = state.visible_tasks();
+    let below = visible.len().saturating_sub(state.offset + rows);
+    if below > 0 {
+        title.push_str(&format!(" - {} more below", below));
+    }
+
+    let items: Vec<ListItem> = visible.iter()
+        .map(|task| {
+            // The TUI always draws in color, so only the option decides
+            let item = ListItem::new(task_line(task));
+            if render::description_style(task.completed, true, state.strike_completed).is_some() {
+                item.style(Style::default().add_modifier(Modifier::DIM | Modifier::CROSSED_OUT))
+            } else {
+                item
+            }
+        })
+        .collect();
+    let todo_list = List::new(items)
+        .block(Block::default()
+            .title(title)
+            .borders(Borders::ALL))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(todo_list, size, &mut state.list_state);
+}
+
+/// Draws and handles input until the user quits, reloading the data file
+/// every `interval` so changes made from other terminals show up
+fn event_loop<B: Backend>(terminal: &mut Terminal<B>, storage: &dyn StorageBackend, interval: Duration, utc: bool, strike_completed: bool) -> Result<(), io::Error> {
+    let last_seen = storage.modified_time();
+    let mut state = UiState::new(storage.load_tasks(), last_seen, utc, strike_completed);
+    let mut last_refresh = Instant::now();
+
+    loop {
+        terminal.draw(|f| draw(f, &mut state, interval))?;
+
+        // Wake up for input, or when it's time to check the file again
+        let timeout = interval.saturating_sub(last_refresh.elapsed());
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) if state.editing_filter => state.edit_filter(key.code),
+                Event::Key(key) => match key.code {
+                    KeyCode::Char('/') => state.editing_filter = true,
+                    KeyCode::Esc if !state.filter.is_empty() => state.edit_filter(KeyCode::Esc),
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Down | KeyCode::Char('j') => state.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => state.select_previous(),
+                    KeyCode::PageDown => state.page_down(),
+                    KeyCode::PageUp => state.page_up(),
+                    _ => {}
+                },
+                Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                    state.click(storage, mouse.column, mouse.row);
+                }
+                _ => {}
+            }
+        }
+
+        if last_refresh.elapsed() >= interval {
+            if let Some(tasks) = reload_if_changed(storage, &mut state.last_seen) {
+                state.tasks = tasks;
+                state.clamp_selection();
+            }
+            last_refresh = Instant::now();
+        }
+    }
+}
+
+/// Runs the interactive TUI
+///
+/// # Arguments
+/// * `storage` - Storage to read tasks from; it should not hold the data file
+///   lock, or other `todo` processes would block while the TUI is open
+/// * `interval` - How often to check the data file for changes
+/// * `utc` - Store completion times in UTC when a task is toggled
+/// * `strike_completed` - Show completed tasks dimmed and struck through
+pub fn run_ui(storage: &dyn StorageBackend, interval: Duration, utc: bool, strike_completed: bool) -> Result<(), io::Error> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -19,27 +352,7 @@ pub fn run_ui() -> Result<(), io::Error> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    terminal.draw(|f| {
-        let mut size: tui::layout::Rect = f.size();
-        let block = Block::default()
-            .title("Todo List")
-            .borders(Borders::ALL);
-        // render outer "Todo List" block and create an inner area for tasks
-        f.render_widget(block, size);
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints([Constraint::Min(0)].as_ref())
-            .split(size);
-        size = chunks[0];
-
-        let todo_block = Block::default()
-            .title("Tasks")
-            .borders(Borders::ALL);
-        f.render_widget(todo_block, size);
-    })?;
-
-    thread::sleep(Duration::from_millis(5000));
+    let result = event_loop(&mut terminal, storage, interval, utc, strike_completed);
 
     // restore terminal
     disable_raw_mode()?;
@@ -50,5 +363,74 @@ pub fn run_ui() -> Result<(), io::Error> {
     )?;
     terminal.show_cursor()?;
 
-    Ok(())
-}
\ No newline at end of file
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{JsonStorage, StorageOptions};
+
+    fn tasks(descriptions: &[&str]) -> Vec<Task> {
+        descriptions.iter()
+            .enumerate()
+            .map(|(index, description)| Task::new(index as u32 + 1, description.to_string(), None, Vec::new(), None))
+            .collect()
+    }
+
+    #[test]
+    fn reload_returns_tasks_only_when_the_file_changed() {
+        let directory = std::env::temp_dir().join(format!("todo-test-reload-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        let storage = JsonStorage::new(&directory, StorageOptions::default());
+        storage.save_tasks(&tasks(&["Walk dog"]));
+
+        let mut last_seen = None;
+        assert_eq!(reload_if_changed(&storage, &mut last_seen).map(|tasks| tasks.len()), Some(1));
+        assert!(reload_if_changed(&storage, &mut last_seen).is_none());
+
+        // Another process saves; bump the time so coarse timestamps still differ
+        storage.save_tasks(&tasks(&["Walk dog", "Buy milk"]));
+        let later = last_seen.unwrap() + Duration::from_secs(5);
+        std::fs::File::options().write(true).open(directory.join(".todo_data.json")).unwrap().set_modified(later).unwrap();
+        let reloaded = reload_if_changed(&storage, &mut last_seen).unwrap();
+        assert_eq!(reloaded[1].description, "Buy milk");
+        assert_eq!(last_seen, Some(later));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn clicks_map_to_rows_inside_the_border() {
+        let area = Rect::new(2, 1, 20, 6);
+        assert_eq!(index_at(area, 0, 5, 2), Some(0));
+        assert_eq!(index_at(area, 10, 5, 5), Some(13));
+        // The border and anything outside the list
+        assert_eq!(index_at(area, 0, 5, 1), None);
+        assert_eq!(index_at(area, 0, 5, 6), None);
+        assert_eq!(index_at(area, 0, 2, 3), None);
+        assert_eq!(index_at(area, 0, 21, 3), None);
+        assert_eq!(index_at(area, 0, 40, 3), None);
+    }
+
+    #[test]
+    fn selection_scrolls_into_view() {
+        // Already visible: the offset stays
+        assert_eq!(follow_selection(5, Some(7), 10), 5);
+        // Above the window: scroll up to it
+        assert_eq!(follow_selection(5, Some(2), 10), 2);
+        // Below the window: scroll just far enough to show it last
+        assert_eq!(follow_selection(0, Some(12), 10), 3);
+        assert_eq!(follow_selection(4, None, 10), 4);
+        assert_eq!(follow_selection(4, Some(9), 0), 4);
+    }
+
+    #[test]
+    fn filter_matches_descriptions_ignoring_case() {
+        let list = tasks(&["Walk dog", "Buy DOG food", "Pay rent"]);
+        let ids: Vec<u32> = filter_tasks(&list, "dog").iter().map(|task| task.id).collect();
+        assert_eq!(ids, [1, 2]);
+        assert_eq!(filter_tasks(&list, "").len(), 3);
+        assert!(filter_tasks(&list, "milk").is_empty());
+    }
+}
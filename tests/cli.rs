@@ -0,0 +1,1004 @@
+// Runs the `todo` binary against a throwaway data directory and checks the
+// documented exit codes: 0 success, 1 not found, 2 bad input, 3 storage error.
+
+use assert_cmd::cargo::cargo_bin;
+use chrono::{Days, Local, NaiveDate};
+use assert_cmd::Command;
+use predicates::prelude::*;
+use predicates::str::contains;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// `todo` with its data and config kept inside `home`
+fn todo(home: &TempDir) -> Command {
+    Command::from_std(todo_process(home))
+}
+
+/// `todo` as a plain process, for tests that run several at once
+fn todo_process(home: &TempDir) -> process::Command {
+    let mut command = process::Command::new(cargo_bin!("todo"));
+    command
+        .env("XDG_DATA_HOME", home.path().join("data"))
+        .env("XDG_CONFIG_HOME", home.path().join("config"))
+        .env_remove("EDITOR")
+        .env_remove("VISUAL");
+    command
+}
+
+/// Holds the data file lock the way a running `todo` command does
+fn hold_lock(home: &TempDir) -> fs::File {
+    let path = home.path().join("data/todo-cli/.todo_data.lock");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let lock = fs::File::create(path).unwrap();
+    lock.lock().unwrap();
+    lock
+}
+
+fn data_file(home: &TempDir) -> PathBuf {
+    home.path().join("data/todo-cli/.todo_data.json")
+}
+
+/// Writes the data file directly, creating its directory
+fn write_data(home: &TempDir, contents: &str) {
+    let path = data_file(home);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+/// Writes the config file the binary reads from `home`
+fn write_config(home: &TempDir, contents: &str) {
+    let path = home.path().join("config/todo-cli/config.toml");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+/// The date `days` from today, as the binary will see it
+fn in_days(days: u64) -> NaiveDate {
+    Local::now().date_naive().checked_add_days(Days::new(days)).unwrap()
+}
+
+/// The date `days` before today, as the binary will see it
+fn days_ago(days: u64) -> NaiveDate {
+    Local::now().date_naive().checked_sub_days(Days::new(days)).unwrap()
+}
+
+/// A current-format data file holding tasks with these IDs and descriptions
+fn data_with(tasks: &[(u32, &str)]) -> String {
+    let tasks: Vec<String> = tasks.iter()
+        .map(|(id, description)| format!(
+            r#"{{"id":{},"description":"{}","priority":null,"completed":false,"created_at":"2025-01-01T00:00:00+00:00"}}"#,
+            id, description
+        ))
+        .collect();
+    format!(r#"{{"version":1,"tasks":[{}]}}"#, tasks.join(","))
+}
+
+#[test]
+fn add_and_list_succeed() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due", "2030-01-01"]).assert().code(0);
+    todo(&home).arg("list").assert().code(0).stdout(contains("Walk dog"));
+}
+
+#[test]
+fn missing_task_exits_with_not_found() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["complete", "42"]).assert().code(1);
+    todo(&home).args(["show", "42"]).assert().code(1);
+}
+
+#[test]
+fn malformed_input_exits_with_bad_input() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due", "someday"]).assert().code(2);
+    todo(&home).args(["add", "Walk dog", "--priority", "urgent"]).assert().code(2);
+}
+
+#[test]
+fn unreadable_file_exits_with_io_error() {
+    let home = TempDir::new().unwrap();
+    let missing = home.path().join("missing.json");
+    todo(&home).arg("import").arg(&missing).assert().code(3).stderr(contains("could not read"));
+}
+
+#[test]
+fn quiet_add_prints_nothing() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["--quiet", "add", "Buy milk"]).assert().code(0).stdout("");
+    todo(&home).args(["list"]).assert().stdout(contains("Buy milk"));
+}
+
+#[test]
+fn warnings_go_to_stderr_only() {
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(3, "Walk dog"), (3, "Buy milk")]));
+    todo(&home).arg("list").assert().code(0)
+        .stdout(contains("Walk dog").and(contains("Warning").not()))
+        .stderr(contains("Warning: duplicate task IDs found (3)"));
+}
+
+#[test]
+fn doctor_flags_duplicate_ids() {
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(1, "Walk dog"), (3, "Buy milk"), (3, "Pay rent")]));
+    todo(&home).arg("doctor").assert().code(3).stdout(contains("Duplicate IDs: 3"));
+
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(1, "Walk dog")]));
+    todo(&home).arg("doctor").assert().code(0).stdout(contains("Duplicate IDs: none"));
+}
+
+#[test]
+fn doctor_repair_renumbers_duplicate_ids() {
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(1, "Walk dog"), (3, "Buy milk"), (3, "Pay rent")]));
+    todo(&home).args(["doctor", "--repair"]).assert().code(0).stdout(contains("#3 -> #4"));
+    todo(&home).arg("doctor").assert().code(0);
+    todo(&home).args(["show", "4"]).assert().code(0).stdout(contains("Pay rent"));
+}
+
+#[test]
+fn doctor_repair_refuses_a_corrupt_file() {
+    let home = TempDir::new().unwrap();
+    let corrupt = data_with(&[(3, "Walk dog"), (3, "Buy milk"), (4, "")]);
+    write_data(&home, &corrupt);
+    todo(&home).args(["doctor", "--repair"]).assert().code(3).stderr(contains("not repairing"));
+    assert_eq!(fs::read_to_string(data_file(&home)).unwrap(), corrupt);
+}
+
+#[test]
+fn checksum_is_verified_on_request() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["--verify-checksum", "list"]).assert().code(0).stderr(contains("CHECKSUM").not());
+
+    let tampered = fs::read_to_string(data_file(&home)).unwrap().replace("Walk dog", "Walk cat");
+    fs::write(data_file(&home), tampered).unwrap();
+    todo(&home).args(["--verify-checksum", "list"]).assert().code(0).stderr(contains("CHECKSUM MISMATCH"));
+    todo(&home).arg("list").assert().code(0).stderr(contains("CHECKSUM").not());
+}
+
+#[test]
+fn list_format_fills_placeholders() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--priority", "high", "--tags", "pets", "--due", "2030-01-02"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    todo(&home).args(["list", "--sort", "id", "--format", "{id}|{desc}|{due}|{priority}|{tags}|[{status}]"])
+        .assert().code(0)
+        .stdout("1|Walk dog|2030-01-02|HIGH|pets|[ ]\n2|Buy milk||||[ ]\n");
+    todo(&home).args(["list", "--sort", "id", "--format", "{desc} ({unknown})"])
+        .assert().code(0)
+        .stdout("Walk dog ({unknown})\nBuy milk ({unknown})\n");
+}
+
+#[test]
+fn count_prints_just_the_number() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["list", "--count"]).assert().code(0).stdout("0\n");
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    todo(&home).args(["complete", "1"]).assert().code(0);
+    todo(&home).args(["list", "--count"]).assert().code(0).stdout("2\n");
+    todo(&home).args(["list", "--count", "--active"]).assert().code(0).stdout("1\n");
+}
+
+#[test]
+fn max_time_keeps_tasks_that_fit_the_budget() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Answer email", "--estimate", "20m"]).assert().code(0);
+    todo(&home).args(["add", "Write report", "--estimate", "1h30m"]).assert().code(0);
+    todo(&home).args(["add", "Someday"]).assert().code(0);
+    todo(&home).args(["list", "--max-time", "45", "--format", "{desc}"]).assert().code(0).stdout("Answer email\n");
+    todo(&home).args(["list", "--max-time", "90", "--count"]).assert().code(0).stdout("2\n");
+    todo(&home).args(["add", "Nap", "--estimate", "soon"]).assert().code(2);
+}
+
+#[test]
+fn agenda_groups_by_day_within_the_window() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due", "today"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--due", "tomorrow"]).assert().code(0);
+    todo(&home).args(["add", "Pay rent", "--due", "+2d", "--priority", "low"]).assert().code(0);
+    todo(&home).args(["add", "Call mom", "--due", "+2d", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["add", "File taxes", "--due", "+3d"]).assert().code(0);
+    todo(&home).args(["add", "Someday"]).assert().code(0);
+
+    let third = in_days(2);
+    let expected = format!(
+        "Today:\n  1: Walk dog\n\nTomorrow:\n  2: Buy milk\n\n{} {}:\n  4: Call mom\n  3: Pay rent\n",
+        third.format("%A"), third.format("%Y-%m-%d")
+    );
+    todo(&home).args(["agenda", "--days", "3"]).assert().code(0).stdout(expected);
+    todo(&home).args(["agenda", "--days", "4"]).assert().code(0).stdout(contains("File taxes"));
+}
+
+#[test]
+fn archive_moves_completed_tasks_and_search_can_include_them() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["add", "Walk cat"]).assert().code(0);
+    todo(&home).args(["complete", "1"]).assert().code(0);
+    todo(&home).arg("archive").assert().code(0);
+
+    todo(&home).args(["list", "--format", "{desc}"]).assert().code(0).stdout("Walk cat\n");
+    todo(&home).args(["search", "walk"]).assert().code(0)
+        .stdout(contains("Walk cat").and(contains("Walk dog").not()));
+    todo(&home).args(["search", "walk", "--include-archived"]).assert().code(0)
+        .stdout(contains("Walk cat").and(contains("Walk dog")));
+}
+
+#[test]
+fn read_only_commands_run_while_the_lock_is_held() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    let lock = hold_lock(&home);
+    todo(&home).arg("list").timeout(Duration::from_secs(10)).assert().code(0).stdout(contains("Walk dog"));
+    todo(&home).args(["show", "1"]).timeout(Duration::from_secs(10)).assert().code(0);
+    drop(lock);
+}
+
+#[test]
+fn changes_wait_for_the_lock() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    let lock = hold_lock(&home);
+
+    let mut add = todo_process(&home);
+    let mut waiting = add.args(["-q", "add", "Buy milk"]).stderr(Stdio::null()).spawn().unwrap();
+    thread::sleep(Duration::from_millis(300));
+    assert!(waiting.try_wait().unwrap().is_none(), "add finished while the lock was held");
+
+    drop(lock);
+    assert!(waiting.wait().unwrap().success());
+    todo(&home).args(["list", "--count"]).assert().stdout("2\n");
+}
+
+#[test]
+fn concurrent_adds_both_survive() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    let adds: Vec<_> = ["Buy milk", "Pay rent", "Call mom"].iter()
+        .map(|description| todo_process(&home).args(["-q", "add", description]).stderr(Stdio::null()).spawn().unwrap())
+        .collect();
+    for mut add in adds {
+        assert!(add.wait().unwrap().success());
+    }
+    todo(&home).args(["list", "--count"]).assert().stdout("4\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn lock_is_released_while_the_editor_is_open() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    // The editor adds a task from a second process; if the edit still held
+    // the lock, that add would wait forever
+    let editor = home.path().join("editor.sh");
+    fs::write(&editor, "#!/bin/sh\n\"$TODO_BIN\" -q add 'Buy milk'\necho 'Walk the dog' > \"$1\"\n").unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+
+    todo(&home)
+        .env("EDITOR", &editor)
+        .env("TODO_BIN", cargo_bin!("todo"))
+        .args(["edit", "1", "--editor"])
+        .timeout(Duration::from_secs(10))
+        .assert().code(0);
+    todo(&home).args(["list", "--sort", "id", "--format", "{desc}"]).assert().stdout("Walk the dog\nBuy milk\n");
+}
+
+#[test]
+fn clear_due_removes_the_date_and_other_edits_keep_it() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due", "2030-01-02"]).assert().code(0);
+    todo(&home).args(["edit", "1", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{due}"]).assert().stdout("2030-01-02\n");
+    todo(&home).args(["edit", "1", "--clear-due"]).assert().code(0);
+    todo(&home).args(["list", "--format", "[{due}]"]).assert().stdout("[]\n");
+    todo(&home).args(["edit", "1", "--clear-due", "--due", "2030-01-02"]).assert().code(2);
+}
+
+#[test]
+fn remove_tags_drops_just_the_named_tag() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--tags", "pets,home,daily"]).assert().code(0);
+    todo(&home).args(["edit", "1", "--remove-tags", "home"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{tags}"]).assert().stdout("daily,pets\n");
+}
+
+#[test]
+fn remove_all_asks_first_unless_told_yes() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    // Piped stdin can't answer the prompt, so nothing is deleted
+    todo(&home).arg("remove-all").write_stdin("y\n").assert().code(2).stderr(contains("--yes"));
+    todo(&home).args(["list", "--count"]).assert().stdout("1\n");
+    todo(&home).args(["remove-all", "--yes"]).assert().code(0);
+    todo(&home).args(["list", "--count"]).assert().stdout("0\n");
+}
+
+#[test]
+fn edit_sets_priority_and_appends_a_tag() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--tags", "pets"]).assert().code(0);
+    todo(&home).args(["edit", "1", "--priority", "high", "--add-tags", "daily"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{priority} {tags}"]).assert().stdout("HIGH daily,pets\n");
+}
+
+#[test]
+fn completions_install_writes_to_the_shell_directory() {
+    let home = TempDir::new().unwrap();
+    todo(&home).env("HOME", home.path()).args(["completions", "fish", "--install"])
+        .assert().code(0).stdout(contains("config/fish/completions/todo.fish"));
+    assert!(!fs::read_to_string(home.path().join("config/fish/completions/todo.fish")).unwrap().is_empty());
+
+    todo(&home).env("HOME", home.path()).args(["completions", "zsh", "--install"]).assert().code(0);
+    assert!(!fs::read_to_string(home.path().join(".zfunc/_todo")).unwrap().is_empty());
+}
+
+#[test]
+fn due_lists_only_tasks_on_that_date() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due", "2030-01-02"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--due", "2030-01-03"]).assert().code(0);
+    todo(&home).args(["add", "Call mom"]).assert().code(0);
+    todo(&home).args(["add", "Pay rent", "--due", "tomorrow"]).assert().code(0);
+    todo(&home).args(["due", "2030-01-02"]).assert().code(0)
+        .stdout(contains("Walk dog").and(contains("Buy milk").not()).and(contains("Call mom").not()));
+    todo(&home).args(["due", "tomorrow"]).assert().code(0)
+        .stdout(contains("Pay rent").and(contains("Walk dog").not()));
+}
+
+#[test]
+fn huge_date_offsets_are_rejected() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due", "+99999999999999d"]).assert().code(2);
+    todo(&home).args(["list", "--since", "-99999999999999d"]).assert().code(2);
+    todo(&home).args(["due", "+99999999999999d"]).assert().code(2);
+}
+
+#[test]
+fn reverse_flips_every_sort_order() {
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(1, "Walk dog"), (2, "Buy milk"), (3, "Call mom")]));
+    todo(&home).args(["list", "--sort", "id", "--reverse", "--format", "{id}"]).assert().stdout("3\n2\n1\n");
+    todo(&home).args(["list", "--sort", "description", "--reverse", "--format", "{id}"]).assert().stdout("1\n3\n2\n");
+}
+
+#[test]
+fn group_by_tag_lists_a_task_under_each_of_its_tags() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--tags", "pets,daily", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--tags", "home"]).assert().code(0);
+    todo(&home).args(["add", "Call mom"]).assert().code(0);
+    todo(&home).args(["list", "--group-by", "tag", "--format", "{id}"])
+        .assert().stdout("daily:\n1\nhome:\n2\npets:\n1\nNo tags:\n3\n");
+    todo(&home).args(["list", "--group-by", "priority", "--format", "{id}"])
+        .assert().stdout("HIGH:\n1\nNo priority:\n2\n3\n");
+}
+
+#[test]
+fn config_sort_applies_until_a_flag_overrides_it() {
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(1, "Walk dog"), (2, "Buy milk"), (3, "Call mom")]));
+    write_config(&home, "sort = \"description\"\n");
+    todo(&home).args(["list", "--format", "{id}"]).assert().stdout("2\n3\n1\n");
+    todo(&home).args(["list", "--sort", "id", "--format", "{id}"]).assert().stdout("1\n2\n3\n");
+    todo(&home).arg("config").assert().code(0).stdout(contains("sort = \"description\""));
+}
+
+#[test]
+fn migrate_backs_up_and_fills_in_new_fields() {
+    let home = TempDir::new().unwrap();
+    let legacy = r#"[{"id":1,"description":"Walk dog","completed":false}]"#;
+    write_data(&home, legacy);
+    todo(&home).arg("migrate").assert().code(0)
+        .stdout(contains("Upgraded format 0 to 1").and(contains("Added `tags` to 1 task")));
+
+    let backup = home.path().join("data/todo-cli/.todo_data.json.v0.bak");
+    assert_eq!(fs::read_to_string(backup).unwrap(), legacy);
+    let migrated = fs::read_to_string(data_file(&home)).unwrap();
+    for field in [r#""version": 1"#, r#""priority": null"#, r#""tags": []"#, r#""uid":"#] {
+        assert!(migrated.contains(field), "{} missing from {}", field, migrated);
+    }
+    todo(&home).arg("migrate").assert().code(0).stdout(contains("Upgraded").not());
+}
+
+#[test]
+fn regex_search_matches_whole_descriptions() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Buy oat milk"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk chocolate"]).assert().code(0);
+    todo(&home).args(["search", "--regex", "^buy .*milk$"]).assert().code(0)
+        .stdout(contains("Buy oat milk").and(contains("chocolate").not()));
+    todo(&home).args(["search", "--regex", "^milk"]).assert().code(0).stdout(contains("Buy").not());
+    todo(&home).args(["search", "--regex", "(milk"]).assert().code(2).stderr(contains("invalid regular expression"));
+}
+
+#[test]
+fn list_title_uses_the_configured_list_color() {
+    let home = TempDir::new().unwrap();
+    write_config(&home, "[list_colors]\nwork = \"magenta\"\n");
+    todo(&home).args(["--list", "work", "add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["--list", "home", "add", "Buy milk"]).assert().code(0);
+    todo(&home).args(["--list", "work", "--color", "always", "list"]).assert().code(0)
+        .stdout(contains("\x1b[35mYour tasks:"));
+    todo(&home).args(["--list", "home", "--color", "always", "list"]).assert().code(0)
+        .stdout(contains("\x1b[35m").not());
+    todo(&home).args(["--list", "work", "--color", "never", "list"]).assert().code(0)
+        .stdout(contains('\x1b').not());
+}
+
+#[test]
+fn plain_emoji_output_is_ascii() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due=-1d", "--priority", "high", "--tags", "pets"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--due", "today", "--priority", "low"]).assert().code(0);
+    todo(&home).args(["add", "Pay rent", "--due", "tomorrow"]).assert().code(0);
+    todo(&home).args(["pin", "3"]).assert().code(0);
+    todo(&home).args(["complete", "2"]).assert().code(0);
+
+    for args in [&["list"][..], &["list", "--compact"], &["list", "--table"], &["show", "1"], &["search", "dog"]] {
+        let output = todo(&home).args(["--plain-emoji", "--color", "always"]).args(args).output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("Walk dog"), "{:?}: {}", args, stdout);
+        assert!(stdout.is_ascii(), "{:?}: {}", args, stdout);
+    }
+}
+
+#[test]
+fn sort_by_urgency_puts_overdue_first() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Water plants", "--due", "+5d", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--due", "tomorrow", "--priority", "low"]).assert().code(0);
+    todo(&home).args(["add", "Pay rent", "--due=-1d", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["add", "Call mom"]).assert().code(0);
+    todo(&home).args(["list", "--sort", "urgency", "--format", "{id}"]).assert().stdout("3\n2\n1\n4\n");
+}
+
+#[test]
+fn strict_dates_refuse_past_due_dates() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["--strict-dates", "add", "Walk dog", "--due=-1d"]).assert().code(2);
+    todo(&home).args(["list", "--count"]).assert().stdout("0\n");
+    todo(&home).args(["--strict-dates", "add", "Walk dog", "--due", "today"]).assert().code(0);
+    todo(&home).args(["--strict-dates", "edit", "1", "--due=-1d"]).assert().code(2);
+    todo(&home).args(["edit", "1", "--due=-1d"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--due=-2d"]).assert().code(0);
+    write_config(&home, "strict_dates = true\n");
+    todo(&home).args(["add", "Pay rent", "--due=-1d"]).assert().code(2);
+}
+
+#[test]
+fn bulk_edit_sets_priority_on_every_tagged_task() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Write report", "--tags", "work"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--tags", "home"]).assert().code(0);
+    todo(&home).args(["add", "Book flights", "--tags", "work,travel", "--priority", "low"]).assert().code(0);
+    todo(&home).args(["bulk-edit", "--tag", "work", "--set-priority", "high"]).assert().code(0);
+    todo(&home).args(["list", "--sort", "id", "--format", "{id} {priority}"]).assert().stdout("1 HIGH\n2 \n3 HIGH\n");
+}
+
+#[test]
+fn huge_snooze_is_refused_before_changing_anything() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due", "2030-01-01"]).assert().code(0);
+    let before = fs::read_to_string(data_file(&home)).unwrap();
+    todo(&home).args(["bulk-edit", "--snooze", "9999999999999"]).assert().code(2);
+    assert_eq!(fs::read_to_string(data_file(&home)).unwrap(), before);
+}
+
+#[test]
+fn template_add_copies_the_saved_fields() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["template", "save", "weekly", "Weekly review", "--priority", "high", "--tags", "work,review", "--estimate", "1h"])
+        .assert().code(0);
+    todo(&home).args(["template", "list"]).assert().code(0).stdout(contains("weekly"));
+    todo(&home).args(["template", "add", "weekly", "--due", "2030-01-02"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{desc}|{priority}|{tags}|{due}"])
+        .assert().stdout("Weekly review|HIGH|review,work|2030-01-02\n");
+    todo(&home).args(["show", "1"]).assert().stdout(contains("1h"));
+    todo(&home).args(["template", "add", "missing"]).assert().code(1);
+}
+
+#[test]
+fn duplicate_add_needs_force() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["add", "walk DOG"]).assert().code(2).stderr(contains("--force"));
+    todo(&home).args(["list", "--count"]).assert().stdout("1\n");
+    todo(&home).args(["add", "walk DOG", "--force"]).assert().code(0);
+    todo(&home).args(["list", "--count"]).assert().stdout("2\n");
+    // Only active tasks count as duplicates
+    todo(&home).args(["complete", "1"]).assert().code(0);
+    todo(&home).args(["complete", "2"]).assert().code(0);
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+}
+
+#[test]
+fn count_by_tallies_priorities_and_tags() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--tags", "pets,daily", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--tags", "daily"]).assert().code(0);
+    todo(&home).args(["add", "Call mom", "--priority", "low"]).assert().code(0);
+    todo(&home).args(["complete", "3"]).assert().code(0);
+
+    todo(&home).args(["count-by", "priority"]).assert().code(0)
+        .stdout("Priority     Tasks\nHIGH         1\nLOW          1\nNo priority  1\n");
+    todo(&home).args(["count-by", "tag"]).assert().code(0)
+        .stdout("Tag      Tasks\ndaily    2\npets     1\nNo tags  1\n");
+    todo(&home).args(["count-by", "priority", "--active-only"]).assert().code(0)
+        .stdout("Priority     Tasks\nHIGH         1\nNo priority  1\n");
+}
+
+#[test]
+fn search_shows_the_overdue_indicator() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due=-2d", "--priority", "high", "--tags", "pets"]).assert().code(0);
+    todo(&home).args(["--plain-emoji", "--color", "never", "search", "dog"]).assert().code(0)
+        .stdout(contains("Walk dog [!] OVERDUE by 2 days.").and(contains("Priority: HIGH")).and(contains("Tags: pets")));
+    todo(&home).args(["--color", "always", "search", "dog"]).assert().code(0)
+        .stdout(contains("\x1b[31mOVERDUE by 2 days"));
+}
+
+#[test]
+fn active_search_skips_completed_tasks() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    todo(&home).args(["add", "Buy bread"]).assert().code(0);
+    todo(&home).args(["complete", "1"]).assert().code(0);
+    todo(&home).args(["search", "buy"]).assert().code(0).stdout(contains("Buy milk").and(contains("Buy bread")));
+    todo(&home).args(["search", "buy", "--active"]).assert().code(0)
+        .stdout(contains("Buy bread").and(contains("Buy milk").not()));
+}
+
+#[cfg(unix)]
+#[test]
+fn editor_result_becomes_the_description() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["edit", "1", "--editor"]).assert().code(2).stderr(contains("$EDITOR is not set"));
+
+    // The editor sees the current description and replaces it
+    let editor = home.path().join("editor.sh");
+    fs::write(&editor, "#!/bin/sh\ngrep -q 'Walk dog' \"$1\" || exit 1\nprintf 'Walk the dog\\n\\n' > \"$1\"\n").unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+    todo(&home).env("EDITOR", &editor).args(["edit", "1", "--editor"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{desc}"]).assert().stdout("Walk the dog\n");
+}
+
+#[test]
+fn labels_are_set_shown_and_filtered() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--label", "red"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    todo(&home).args(["edit", "2", "--label", "blue"]).assert().code(0);
+    todo(&home).args(["add", "Call mom", "--label", "red"]).assert().code(0);
+
+    todo(&home).args(["list", "--label", "red", "--sort", "id", "--format", "{id}"]).assert().stdout("1\n3\n");
+    todo(&home).args(["list", "--label", "blue", "--format", "{id}"]).assert().stdout("2\n");
+    todo(&home).args(["--color", "always", "list", "--label", "blue", "--compact"]).assert()
+        .stdout(contains("\x1b[34m●"));
+    todo(&home).args(["edit", "2", "--clear-label"]).assert().code(0);
+    todo(&home).args(["list", "--label", "blue", "--count"]).assert().stdout("0\n");
+}
+
+#[test]
+fn pinned_tasks_lead_in_every_sort() {
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(1, "Walk dog"), (2, "Buy milk"), (3, "Call mom"), (4, "Pay rent")]));
+    todo(&home).args(["pin", "4"]).assert().code(0);
+    todo(&home).args(["pin", "1"]).assert().code(0);
+    todo(&home).args(["list", "--sort", "id", "--format", "{id}"]).assert().stdout("1\n4\n2\n3\n");
+    todo(&home).args(["list", "--sort", "description", "--format", "{id}"]).assert().stdout("4\n1\n2\n3\n");
+    todo(&home).args(["unpin", "1"]).assert().code(0);
+    todo(&home).args(["list", "--sort", "id", "--format", "{id}"]).assert().stdout("4\n1\n2\n3\n");
+}
+
+#[test]
+fn id_ranges_expand_and_oversized_ones_are_refused() {
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(1, "Walk dog"), (2, "Buy milk"), (3, "Call mom"), (4, "Pay rent")]));
+    todo(&home).args(["complete-tasks", "1-50000"]).assert().code(2).stderr(contains("highest ID is 4"));
+    todo(&home).args(["complete-tasks", "3-2"]).assert().code(2);
+    todo(&home).args(["complete-tasks", "2-3", "1"]).assert().code(0);
+    todo(&home).args(["list", "--active", "--format", "{id}"]).assert().stdout("4\n");
+    todo(&home).args(["remove-tasks", "1-3"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{id}"]).assert().stdout("4\n");
+}
+
+#[test]
+fn reminder_banner_counts_overdue_and_due_today() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due=-1d"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--due=-3d"]).assert().code(0);
+    todo(&home).args(["add", "Call mom", "--due", "today"]).assert().code(0);
+    todo(&home).args(["add", "Pay rent", "--due", "tomorrow"]).assert().code(0);
+    todo(&home).args(["list", "--count"]).assert().stdout("4\n").stderr("! 2 tasks overdue, 1 due today\n");
+    todo(&home).args(["--no-reminders", "list", "--count"]).assert().stderr("");
+    write_config(&home, "reminders = false\n");
+    todo(&home).args(["list", "--count"]).assert().stderr("");
+}
+
+#[test]
+fn first_and_last_follow_the_list_order() {
+    let home = TempDir::new().unwrap();
+    write_data(&home, &data_with(&[(1, "Walk dog"), (2, "Buy milk"), (3, "Call mom"), (4, "Pay rent")]));
+    write_config(&home, "sort = \"description\"\n");
+    todo(&home).args(["complete", "--first", "0"]).assert().code(2).stderr(contains("0 is not in 1.."));
+    todo(&home).args(["remove", "--last", "0"]).assert().code(2);
+    // By description: Buy milk, Call mom, Pay rent, Walk dog
+    todo(&home).args(["complete", "--first", "2"]).assert().code(0);
+    todo(&home).args(["list", "--active", "--sort", "id", "--format", "{id}"]).assert().stdout("1\n4\n");
+    todo(&home).args(["remove", "--last", "1"]).assert().code(0);
+    todo(&home).args(["list", "--sort", "id", "--format", "{id}"]).assert().stdout("2\n3\n4\n");
+}
+
+#[test]
+fn json_lines_print_one_task_per_line() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk \"dog\"", "--tags", "pets,daily"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    for args in [["export", "--json-lines"], ["list", "--json-lines"]] {
+        let output = todo(&home).args(args).output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let tasks: Vec<serde_json::Value> = stdout.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(tasks.len(), 2, "{:?}", args);
+        assert_eq!(tasks[0]["description"], "Walk \"dog\"");
+        assert_eq!(tasks[0]["tags"], serde_json::json!(["daily", "pets"]));
+        assert_eq!(tasks[1]["id"], 2);
+    }
+}
+
+#[test]
+fn export_output_writes_the_file() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    let file = home.path().join("exports/nested/tasks.json");
+    todo(&home).args(["export", "--format", "json", "--output"]).arg(&file).assert().code(0).stdout(contains("Exported 1 tasks"));
+    let tasks: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    assert_eq!(tasks[0]["description"], "Walk dog");
+
+    // A directory where the file should go can't be written
+    let blocked = home.path().join("blocked");
+    fs::create_dir(&blocked).unwrap();
+    todo(&home).args(["export", "--output"]).arg(&blocked).assert().code(3).stderr(contains("Error"));
+}
+
+#[test]
+fn unwritable_data_directory_refuses_changes() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    let before = fs::read_to_string(data_file(&home)).unwrap();
+    // A directory in the way of the write probe fails even for root, which
+    // ignores read-only permissions
+    fs::create_dir(home.path().join("data/todo-cli/.todo_write_test")).unwrap();
+
+    todo(&home).args(["add", "Buy milk"]).assert().code(3)
+        .stderr(contains("can't save changes").and(contains("nothing was changed")));
+    todo(&home).args(["complete", "1"]).assert().code(3);
+    assert_eq!(fs::read_to_string(data_file(&home)).unwrap(), before);
+    todo(&home).arg("list").assert().code(0).stdout(contains("Walk dog"));
+}
+
+#[test]
+fn version_json_names_the_package() {
+    let home = TempDir::new().unwrap();
+    let output = todo(&home).args(["version", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let version: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(version["name"], env!("CARGO_PKG_NAME"));
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert!(!version["version"].as_str().unwrap().is_empty());
+}
+
+#[test]
+fn ambiguous_match_without_a_terminal_lists_the_candidates() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    todo(&home).args(["add", "Buy bread"]).assert().code(0);
+    todo(&home).args(["complete", "--match", "buy"]).write_stdin("1\n").assert().code(2)
+        .stderr(contains("matches 2 tasks").and(contains("#1 Buy milk")).and(contains("#2 Buy bread")));
+    todo(&home).args(["list", "--active", "--count"]).assert().stdout("2\n");
+    todo(&home).args(["complete", "--match", "milk"]).assert().code(0);
+}
+
+#[test]
+fn tags_come_out_sorted_whatever_the_input_order() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--tags", "pets,daily,chores"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{tags}"]).assert().stdout("chores,daily,pets\n");
+    todo(&home).args(["edit", "1", "--add-tags", "zoo,aardvark"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{tags}"]).assert().stdout("aardvark,chores,daily,pets,zoo\n");
+    todo(&home).args(["edit", "1", "--tags", "b,a"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{tags}"]).assert().stdout("a,b\n");
+    // Files written by hand or older versions are sorted on load
+    write_data(&home, r#"{"version":1,"tasks":[{"id":1,"description":"Walk dog","tags":["pets","daily"],"completed":false,"created_at":"2025-01-01T00:00:00+00:00"}]}"#);
+    todo(&home).args(["list", "--format", "{tags}"]).assert().stdout("daily,pets\n");
+}
+
+#[test]
+fn large_imports_report_progress() {
+    let home = TempDir::new().unwrap();
+    let small = home.path().join("small.txt");
+    fs::write(&small, "Call mom\nPay rent\nFile taxes\n").unwrap();
+    todo(&home).args(["import", "--format", "todo-txt"]).arg(&small).assert().code(0)
+        .stdout(contains("Imported 3 tasks from").and(contains("Imported 3/").not()));
+
+    let large = home.path().join("large.txt");
+    fs::write(&large, (1..=600).map(|n| format!("Task {}\n", n)).collect::<String>()).unwrap();
+    todo(&home).args(["import", "--format", "todo-txt"]).arg(&large).assert().code(0)
+        .stdout(contains("Imported 250/600\nImported 500/600\nImported 600 tasks from"));
+    todo(&home).args(["-q", "import", "--format", "todo-txt"]).arg(&large).assert().code(0).stdout("");
+    todo(&home).args(["list", "--count"]).assert().stdout("1203\n");
+}
+
+#[test]
+fn skip_duplicates_leaves_out_tasks_already_listed() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--due", "2030-01-02"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    let file = home.path().join("todo.txt");
+    // Same description and due date; same description, other date; new task
+    fs::write(&file, "walk dog due:2030-01-02\nBuy milk due:2030-01-05\nCall mom\n").unwrap();
+
+    todo(&home).args(["import", "--format", "todo-txt", "--skip-duplicates"]).arg(&file).assert().code(0)
+        .stdout(contains("Imported 2 tasks from").and(contains("skipped 1 duplicates")));
+    todo(&home).args(["list", "--sort", "id", "--format", "{desc} {due}"])
+        .assert().stdout("Walk dog 2030-01-02\nBuy milk \nBuy milk 2030-01-05\nCall mom \n");
+}
+
+#[test]
+fn only_color_always_forces_escapes_into_piped_output() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["--color", "always", "list"]).assert().code(0).stdout(contains('\x1b'));
+    todo(&home).args(["--color", "never", "list"]).assert().code(0).stdout(contains('\x1b').not());
+    todo(&home).args(["--color", "auto", "list"]).assert().code(0).stdout(contains('\x1b').not());
+    write_config(&home, "color = true\n");
+    todo(&home).arg("list").assert().code(0).stdout(contains('\x1b').not());
+    todo(&home).args(["--color", "always", "list"]).assert().code(0).stdout(contains('\x1b'));
+    write_config(&home, "color = false\n");
+    todo(&home).args(["--color", "always", "list"]).assert().code(0).stdout(contains('\x1b'));
+}
+
+#[test]
+fn due_and_report_headings_follow_the_date_format() {
+    let home = TempDir::new().unwrap();
+    write_config(&home, "date_format = \"%d/%m/%Y\"\n");
+    let day = in_days(3);
+    let shown = day.format("%d/%m/%Y").to_string();
+    todo(&home).args(["add", "Walk dog", "--due", &day.to_string()]).assert().code(0);
+    todo(&home).args(["due", &day.to_string()]).assert().code(0)
+        .stdout(contains(format!("Tasks due on {}:", shown)));
+    todo(&home).args(["due", &in_days(4).to_string()]).assert().code(0)
+        .stdout(contains(format!("No tasks due on {}", in_days(4).format("%d/%m/%Y"))));
+    let today = in_days(0).format("%d/%m/%Y").to_string();
+    todo(&home).args(["report", "--period", "week"]).assert().code(0)
+        .stdout(contains(format!("to {}: 0 tasks", today)).and(contains(in_days(0).to_string()).not()));
+}
+
+#[test]
+fn search_sort_orders_matches_by_priority() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Buy milk", "--priority", "low"]).assert().code(0);
+    todo(&home).args(["add", "Buy bread", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["add", "Walk dog", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["add", "Buy eggs", "--priority", "medium"]).assert().code(0);
+    todo(&home).args(["--color", "never", "search", "buy", "--sort", "priority", "--ids-only"])
+        .assert().code(0).stdout("2\n4\n1\n");
+}
+
+#[test]
+fn all_prints_only_the_sections_with_tasks() {
+    let home = TempDir::new().unwrap();
+    todo(&home).arg("all").assert().code(0).stdout(contains("Nothing due in the next 7 days"));
+    todo(&home).args(["add", "Walk dog", "--due", &in_days(0).to_string()]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--due", &in_days(3).to_string()]).assert().code(0);
+    todo(&home).args(["add", "Plan trip", "--due", &in_days(30).to_string()]).assert().code(0);
+    todo(&home).args(["--color", "never", "all"]).assert().code(0)
+        .stdout(contains("== Due today (1) ==").and(contains("== Upcoming (1) =="))
+            .and(contains("Overdue").not()).and(contains("No due date").not()).and(contains("Plan trip").not()));
+    todo(&home).args(["add", "Call mom"]).assert().code(0);
+    todo(&home).args(["--color", "never", "all"]).assert().code(0)
+        .stdout(contains("== No due date (1) ==").and(contains("Call mom")));
+}
+
+#[test]
+fn default_priority_applies_only_when_none_is_given() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    write_config(&home, "default_priority = \"medium\"\n");
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    todo(&home).args(["add", "Call mom", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["list", "--sort", "id", "--format", "{id} {priority}"])
+        .assert().code(0).stdout("1 \n2 MED\n3 HIGH\n");
+}
+
+#[test]
+fn data_file_from_a_newer_version_exits_with_io_error() {
+    let home = TempDir::new().unwrap();
+    let newer = r#"{"version":99,"tasks":[]}"#;
+    write_data(&home, newer);
+    todo(&home).arg("list").assert().code(3).stderr(contains("newer version"));
+    todo(&home).args(["add", "Walk dog"]).assert().code(3);
+    assert_eq!(fs::read_to_string(data_file(&home)).unwrap(), newer);
+}
+
+#[test]
+fn read_only_commands_leave_a_legacy_file_alone() {
+    let home = TempDir::new().unwrap();
+    let legacy = r#"[{"id":1,"description":"Walk dog","priority":null,"completed":false,"created_at":"2025-01-01T00:00:00+00:00"}]"#;
+    write_data(&home, legacy);
+    todo(&home).arg("list").assert().code(0).stdout(contains("Walk dog"));
+    assert_eq!(fs::read_to_string(data_file(&home)).unwrap(), legacy);
+
+    todo(&home).arg("migrate").assert().code(0).stdout(contains("Added `uid`"));
+    assert!(fs::read_to_string(data_file(&home)).unwrap().contains("\"uid\""));
+}
+
+#[test]
+fn uid_prefixes_stand_in_for_ids() {
+    let home = TempDir::new().unwrap();
+    let task = |id: u32, uid: &str, description: &str| format!(
+        r#"{{"id":{},"uid":"{}","description":"{}","priority":null,"completed":false,"created_at":"2025-01-01T00:00:00+00:00"}}"#,
+        id, uid, description);
+    write_data(&home, &format!(r#"{{"version":1,"tasks":[{},{},{}]}}"#,
+        task(1, "k3xq", "Walk dog"), task(2, "k3ab", "Buy milk"), task(3, "zz22", "Call mom")));
+    todo(&home).args(["complete", "K3X"]).assert().code(0);
+    todo(&home).args(["complete", "k3"]).assert().code(2).stderr(contains("matches tasks #1, #2"));
+    todo(&home).args(["complete", "q"]).assert().code(1);
+    todo(&home).args(["show", "zz"]).assert().code(0).stdout(contains("Call mom"));
+    todo(&home).args(["--plain-emoji", "list", "--sort", "id", "--format", "{id}{status}"]).assert().code(0)
+        .stdout("1x\n2 \n3 \n");
+}
+
+#[test]
+fn merging_a_todo_txt_file_is_refused() {
+    let home = TempDir::new().unwrap();
+    let file = home.path().join("todo.txt");
+    fs::write(&file, "Call mom\nPay rent\nFile taxes\n").unwrap();
+    todo(&home).args(["import", "--merge", "--format", "todo-txt"]).arg(&file).assert().code(2);
+    todo(&home).args(["import", "--format", "todo-txt"]).arg(&file).assert().code(0);
+    todo(&home).arg("list").assert().code(0)
+        .stdout(contains("Call mom").and(contains("Pay rent")).and(contains("File taxes")));
+}
+
+#[test]
+fn merging_the_largest_id_is_refused_before_changing_anything() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    let before = fs::read_to_string(data_file(&home)).unwrap();
+    let file = home.path().join("tasks.json");
+    fs::write(&file, format!(
+        r#"[{{"id":5,"description":"Buy milk","priority":null,"completed":false,"created_at":"2025-01-01T00:00:00+00:00"}},
+            {{"id":{},"description":"Call mom","priority":null,"completed":false,"created_at":"2025-01-01T00:00:00+00:00"}}]"#,
+        u32::MAX)).unwrap();
+    todo(&home).args(["import", "--merge"]).arg(&file).assert().code(2).stderr(contains("too large"));
+    assert_eq!(fs::read_to_string(data_file(&home)).unwrap(), before);
+}
+
+#[test]
+fn edit_tags_flag_replaces_tags_and_conflicts_with_clear() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--tags", "pets"]).assert().code(0);
+    todo(&home).args(["edit", "1", "--tags", "daily,outdoor"]).assert().code(0);
+    todo(&home).args(["edit", "1", "-t", "morning"]).assert().code(0);
+    todo(&home).args(["list", "--format", "{tags}"]).assert().code(0).stdout(contains("morning").and(contains("pets").not()));
+    todo(&home).args(["edit", "1", "--tags", "x", "--clear-tags"]).assert().code(2);
+    todo(&home).args(["edit", "--help"]).assert().code(0).stdout(contains("--tags <TAGS>"));
+}
+
+#[test]
+fn csv_fields_pick_the_columns_and_their_order() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--priority", "high", "--tags", "pets,daily", "--due", "2030-01-02"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk, eggs"]).assert().code(0);
+    todo(&home).args(["export", "--format", "csv", "--fields", "due,id,tags,description"]).assert().code(0)
+        .stdout("due,id,tags,description\r\n2030-01-02,1,daily pets,Walk dog\r\n,2,,\"Buy milk, eggs\"\r\n");
+    todo(&home).args(["export", "--format", "csv", "--fields", "id,colour"]).assert().code(2);
+}
+
+#[test]
+fn dash_reads_descriptions_from_stdin() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "-", "--tags", "home"]).write_stdin("  Walk dog\n").assert().code(0);
+    todo(&home).args(["add", "-", "--split-lines"]).write_stdin("Buy milk\n\n  Call mom\n").assert().code(0);
+    todo(&home).args(["add", "-"]).write_stdin(" \n").assert().code(2).stderr(contains("stdin was empty"));
+    todo(&home).args(["list", "--sort", "id", "--format", "{id} {desc} {tags}"]).assert().code(0)
+        .stdout("1 Walk dog home\n2 Buy milk \n3 Call mom \n");
+}
+
+#[test]
+fn add_many_reads_tags_and_due_dates_from_each_line() {
+    let home = TempDir::new().unwrap();
+    let file = home.path().join("tasks.txt");
+    fs::write(&file, "Walk dog\n\nBuy milk #errands #Home due:2030-01-05\n#orphan\nCall mom due:2030-01-02\n").unwrap();
+    todo(&home).arg("add-many").arg(&file).assert().code(2)
+        .stderr(contains("line 4 has no description"))
+        .stdout(contains("Added 3 tasks from").and(contains("skipped 1 lines")));
+    todo(&home).args(["list", "--sort", "id", "--format", "{desc}|{tags}|{due}"]).assert().code(0)
+        .stdout("Walk dog||\nBuy milk|errands,Home|2030-01-05\nCall mom||2030-01-02\n");
+}
+
+#[test]
+fn adding_past_max_tasks_is_refused_or_warned_about() {
+    let home = TempDir::new().unwrap();
+    write_config(&home, "max_tasks = 2\n");
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk"]).assert().code(0);
+    todo(&home).args(["add", "Call mom"]).assert().code(2).stderr(contains("max_tasks limit is 2"));
+    todo(&home).args(["list", "--count"]).assert().stdout("2\n");
+
+    write_config(&home, "max_tasks = 2\nmax_tasks_action = \"warn\"\n");
+    todo(&home).args(["add", "Call mom"]).assert().code(0).stderr(contains("Warning: the list has 3 tasks"));
+    todo(&home).args(["list", "--count"]).assert().stdout("3\n");
+}
+
+#[test]
+fn first_run_creates_an_empty_data_file_and_welcomes_once() {
+    let home = TempDir::new().unwrap();
+    todo(&home).arg("list").assert().code(0).stderr(contains("Welcome to todo!"));
+    let data: serde_json::Value = serde_json::from_str(&fs::read_to_string(data_file(&home)).unwrap()).unwrap();
+    assert_eq!(data["tasks"], serde_json::json!([]));
+    todo(&home).arg("list").assert().code(0).stderr(contains("Welcome").not());
+
+    let other = TempDir::new().unwrap();
+    todo(&other).args(["add", "Walk dog"]).assert().code(0).stderr(contains("Welcome").not());
+}
+
+#[test]
+fn ids_only_prints_just_the_filtered_ids() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog", "--priority", "low"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk", "--priority", "high"]).assert().code(0);
+    todo(&home).args(["add", "Call mom"]).assert().code(0);
+    todo(&home).args(["complete", "2"]).assert().code(0);
+    todo(&home).args(["list", "--ids-only", "--sort", "id"]).assert().code(0).stdout("1\n2\n3\n");
+    todo(&home).args(["list", "--ids-only", "--active", "--sort", "id", "--reverse"]).assert().code(0).stdout("3\n1\n");
+    todo(&home).args(["search", "nothing", "--ids-only"]).assert().stdout("");
+}
+
+#[test]
+fn print0_separates_with_nul_bytes_and_no_newlines() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["add", "Walk dog"]).assert().code(0);
+    todo(&home).args(["add", "Buy milk\nand eggs"]).assert().code(0);
+    let output = todo(&home).args(["list", "--print0", "--sort", "id"]).output().unwrap();
+    assert_eq!(output.stdout, b"Walk dog\0Buy milk\nand eggs\0");
+    let output = todo(&home).args(["search", "milk", "--print0", "--ids-only"]).output().unwrap();
+    assert_eq!(output.stdout, b"2\0");
+    assert!(!output.stdout.contains(&b'\n'));
+}
+
+#[test]
+fn urgent_includes_tasks_overdue_by_ten_days() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["--no-reminders", "add", "Pay rent", "--due", &days_ago(10).to_string()]).assert().code(0);
+    todo(&home).args(["--no-reminders", "add", "Walk dog", "--due", &in_days(3).to_string()]).assert().code(0);
+    todo(&home).args(["--no-reminders", "add", "Plan trip", "--due", &in_days(4).to_string()]).assert().code(0);
+    todo(&home).args(["--no-reminders", "list", "--urgent", "--ids-only", "--sort", "id"]).assert().code(0).stdout("1\n2\n");
+}
+
+#[test]
+fn include_completed_lets_urgent_show_completed_tasks() {
+    let home = TempDir::new().unwrap();
+    todo(&home).args(["--no-reminders", "add", "Pay rent", "--due", &days_ago(2).to_string()]).assert().code(0);
+    todo(&home).args(["--no-reminders", "add", "Walk dog", "--due", &in_days(1).to_string()]).assert().code(0);
+    todo(&home).args(["--no-reminders", "add", "Call mom", "--due", &in_days(1).to_string()]).assert().code(0);
+    todo(&home).args(["--no-reminders", "complete", "1"]).assert().code(0);
+    todo(&home).args(["--no-reminders", "complete", "2"]).assert().code(0);
+    todo(&home).args(["--no-reminders", "list", "--urgent", "--ids-only", "--sort", "id"]).assert().code(0).stdout("3\n");
+    todo(&home).args(["--no-reminders", "list", "--urgent", "--include-completed", "--ids-only", "--sort", "id"])
+        .assert().code(0).stdout("1\n2\n3\n");
+    todo(&home).args(["list", "--include-completed"]).assert().code(2);
+}